@@ -0,0 +1,231 @@
+//! Aho–Corasick automaton for matching many byte patterns in a single pass.
+
+use std::collections::VecDeque;
+use std::iter;
+
+const ROOT: u32 = 0;
+
+struct Node<V> {
+    /// sorted by byte
+    children: Vec<(u8, u32)>,
+    /// the node for the longest proper suffix of this node that is also in the trie
+    fail:     u32,
+    /// the nearest node along the failure links that ends a pattern, or the root if none does
+    dict:     u32,
+    /// length of the prefix spelled by this node, and hence of the pattern ending here
+    depth:    usize,
+    /// value of the pattern ending exactly at this node
+    output:   Option<V>,
+}
+
+impl<V> Node<V> {
+    fn child(&self, byte: u8) -> Option<u32> {
+        let index = self.children.binary_search_by_key(&byte, |&(b, _)| b).ok()?;
+        Some(self.children[index].1)
+    }
+}
+
+pub struct AhoCorasick<V> {
+    nodes: Vec<Node<V>>,
+}
+
+/// A match spanning `start..end` in the scanned bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<V> {
+    pub start: usize,
+    pub end:   usize,
+    pub value: V,
+}
+
+impl<V: Copy> AhoCorasick<V> {
+    /// Builds the automaton from `(pattern, value)` pairs.
+    ///
+    /// If a pattern is repeated, the last value wins.
+    pub fn new<P: AsRef<[u8]>>(patterns: impl IntoIterator<Item = (P, V)>) -> Self {
+        Self::build(patterns.into_iter().map(|(pattern, value)| (pattern.as_ref().to_vec(), value)))
+    }
+
+    /// Builds the automaton for the reversed patterns,
+    /// which finds matches when scanning bytes from the end.
+    pub fn new_reversed<P: AsRef<[u8]>>(patterns: impl IntoIterator<Item = (P, V)>) -> Self {
+        Self::build(patterns.into_iter().map(|(pattern, value)| {
+            let mut pattern = pattern.as_ref().to_vec();
+            pattern.reverse();
+            (pattern, value)
+        }))
+    }
+
+    fn build(patterns: impl Iterator<Item = (Vec<u8>, V)>) -> Self {
+        let mut nodes = vec![Node {
+            children: Vec::new(),
+            fail:     ROOT,
+            dict:     ROOT,
+            depth:    0,
+            output:   None,
+        }];
+
+        for (pattern, value) in patterns {
+            assert!(!pattern.is_empty(), "empty patterns are not supported");
+
+            let mut state = ROOT;
+            for &byte in &pattern {
+                state = match nodes[state as usize].child(byte) {
+                    Some(child) => child,
+                    None => {
+                        let child = nodes.len() as u32;
+                        let depth = nodes[state as usize].depth + 1;
+                        nodes.push(Node {
+                            children: Vec::new(),
+                            fail: ROOT,
+                            dict: ROOT,
+                            depth,
+                            output: None,
+                        });
+                        let children = &mut nodes[state as usize].children;
+                        let index = children.partition_point(|&(b, _)| b < byte);
+                        children.insert(index, (byte, child));
+                        child
+                    }
+                };
+            }
+            nodes[state as usize].output = Some(value);
+        }
+
+        let mut automaton = Self { nodes };
+
+        // breadth-first, so that the failure links of shallower nodes are ready
+        let mut queue: VecDeque<u32> = VecDeque::from([ROOT]);
+        while let Some(parent) = queue.pop_front() {
+            for i in 0..automaton.nodes[parent as usize].children.len() {
+                let (byte, child) = automaton.nodes[parent as usize].children[i];
+                let fail = if parent == ROOT {
+                    ROOT
+                } else {
+                    automaton.step(automaton.nodes[parent as usize].fail, byte)
+                };
+
+                let fail_node = &automaton.nodes[fail as usize];
+                let dict = if fail_node.output.is_some() { fail } else { fail_node.dict };
+                let node = &mut automaton.nodes[child as usize];
+                node.fail = fail;
+                node.dict = dict;
+                queue.push_back(child);
+            }
+        }
+
+        automaton
+    }
+
+    fn step(&self, mut state: u32, byte: u8) -> u32 {
+        loop {
+            if let Some(child) = self.nodes[state as usize].child(byte) {
+                return child;
+            }
+            if state == ROOT {
+                return ROOT;
+            }
+            state = self.nodes[state as usize].fail;
+        }
+    }
+
+    /// The patterns ending at `state`, longest first, as `(length, value)` pairs.
+    fn outputs(&self, state: u32) -> impl Iterator<Item = (usize, V)> + '_ {
+        iter::successors(Some(state), |&state| Some(self.nodes[state as usize].dict))
+            .take_while(|&state| state != ROOT)
+            .filter_map(|state| {
+                let node = &self.nodes[state as usize];
+                node.output.map(|value| (node.depth, value))
+            })
+    }
+
+    /// Yields every match, including overlapping ones.
+    ///
+    /// Matches are ordered by `end`, and longest first among those ending at the same position.
+    pub fn find_iter<'t>(
+        &'t self,
+        haystack: impl IntoIterator<Item = u8> + 't,
+    ) -> impl Iterator<Item = Match<V>> + 't {
+        haystack
+            .into_iter()
+            .enumerate()
+            .scan(ROOT, move |state, (index, byte)| {
+                *state = self.step(*state, byte);
+                let end = index + 1;
+                Some(self.outputs(*state).map(move |(len, value)| Match {
+                    start: end - len,
+                    end,
+                    value,
+                }))
+            })
+            .flatten()
+    }
+
+    /// Returns the value of the match that starts first,
+    /// preferring the longest one if several start at the same position.
+    pub fn find_first(&self, haystack: impl IntoIterator<Item = u8>) -> Option<V> {
+        let mut state = ROOT;
+        let mut best: Option<(usize, V)> = None;
+
+        for (index, byte) in haystack.into_iter().enumerate() {
+            state = self.step(state, byte);
+            let end = index + 1;
+
+            // the first output is the longest, so it starts earliest
+            if let Some((len, value)) = self.outputs(state).next() {
+                if best.is_none_or(|(start, _)| end - len <= start) {
+                    best = Some((end - len, value));
+                }
+            }
+
+            // later matches cannot start before the prefix currently being tracked
+            if let Some((start, value)) = best {
+                if start < end - self.nodes[state as usize].depth {
+                    return Some(value);
+                }
+            }
+        }
+
+        best.map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AhoCorasick, Match};
+
+    #[test]
+    fn test_find_iter() {
+        let ac = AhoCorasick::new([("he", 1), ("she", 2), ("his", 3), ("hers", 4)]);
+        let matches: Vec<_> = ac.find_iter(*b"ushers").collect();
+        assert_eq!(
+            matches,
+            [
+                Match { start: 1, end: 4, value: 2 },
+                Match { start: 2, end: 4, value: 1 },
+                Match { start: 2, end: 6, value: 4 },
+            ]
+        );
+
+        let ac = AhoCorasick::new([("a", 1), ("aa", 2), ("aaa", 3)]);
+        assert_eq!(ac.find_iter(*b"aaa").map(|m| m.value).collect::<Vec<_>>(), [1, 2, 1, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_find_first() {
+        // "bc" ends first, but "abcd" starts first
+        let ac = AhoCorasick::new([("abcd", 1), ("bc", 2), ("c", 3)]);
+        assert_eq!(ac.find_first(*b"xabcd"), Some(1));
+        // the earlier start falls through, so the best match so far is kept
+        assert_eq!(ac.find_first(*b"xabcx"), Some(2));
+        assert_eq!(ac.find_first(*b"xyz"), None);
+
+        // the longest match wins among those starting at the same position
+        let patterns = [("ab", 2), ("abcd", 1)];
+        let ac = AhoCorasick::new(patterns);
+        assert_eq!(ac.find_first(*b"xabcdy"), Some(1));
+        assert_eq!(ac.find_first(*b"xabcab"), Some(2));
+        let rev = AhoCorasick::new_reversed(patterns);
+        assert_eq!(rev.find_first(b"xabcdy".iter().rev().copied()), Some(1));
+        assert_eq!(rev.find_first(b"abcdab".iter().rev().copied()), Some(2));
+    }
+}
@@ -1,5 +1,33 @@
+use std::simd::cmp::{SimdPartialEq, SimdPartialOrd};
+use std::simd::u8x64;
 use std::{array, iter, mem};
 
+use crate::aho_corasick::AhoCorasick;
+
+/// Spelled-out digits of a language, indexed by value.
+pub struct Language {
+    pub words: [&'static str; 10],
+}
+
+pub const ENGLISH: Language = Language {
+    words: ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"],
+};
+pub const FRENCH: Language = Language {
+    words: ["zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf"],
+};
+pub const GERMAN: Language = Language {
+    words: ["null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun"],
+};
+
+const NUMERALS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+impl Language {
+    /// Word-value pairs, including the word for zero only if `zero` is set.
+    pub fn digits(&self, zero: bool) -> impl Iterator<Item = (&'static str, u32)> + '_ {
+        self.words.iter().copied().zip(0..).skip(usize::from(!zero))
+    }
+}
+
 #[aoc_runner_derive::aoc(day1, part1)]
 pub fn part1(input: &str) -> u32 {
     input
@@ -16,7 +44,56 @@ pub fn part1(input: &str) -> u32 {
         .sum()
 }
 
-#[aoc_runner_derive::aoc(day1, part2)]
+#[aoc_runner_derive::aoc(day1, part1, Simd)]
+pub fn part1_simd(input: &str) -> u32 {
+    struct Lines {
+        sum:   u32,
+        first: Option<u8>,
+        last:  u8,
+    }
+
+    impl Lines {
+        fn visit(&mut self, byte: u8) {
+            if byte == b'\n' {
+                let first = self.first.take().expect("line has no digits");
+                self.sum += u32::from(first * 10 + self.last);
+            } else {
+                let digit = byte - b'0';
+                self.first.get_or_insert(digit);
+                self.last = digit;
+            }
+        }
+    }
+
+    let bytes = input.as_bytes();
+    let mut lines = Lines { sum: 0, first: None, last: 0 };
+
+    let (chunks, rest) = bytes.as_chunks();
+    for chunk in chunks {
+        let simd = u8x64::from_array(*chunk);
+        // bytes below b'0' wrap around and compare greater than 9
+        let digits = (simd - u8x64::splat(b'0')).simd_le(u8x64::splat(9));
+        let newlines = simd.simd_eq(u8x64::splat(b'\n'));
+
+        let mut bits = (digits | newlines).to_bitmask();
+        while bits != 0 {
+            lines.visit(chunk[bits.trailing_zeros() as usize]);
+            bits &= bits - 1;
+        }
+    }
+    for &byte in rest {
+        if byte.is_ascii_digit() || byte == b'\n' {
+            lines.visit(byte);
+        }
+    }
+
+    if !bytes.ends_with(b"\n") && !bytes.is_empty() {
+        lines.visit(b'\n');
+    }
+    lines.sum
+}
+
+#[aoc_runner_derive::aoc(day1, part2, Trie)]
 pub fn part2(input: &str) -> u32 {
     struct Trie {
         children: [Option<Box<Trie>>; 36],
@@ -36,27 +113,15 @@ pub fn part2(input: &str) -> u32 {
         }
 
         fn lookup(&self, ch: char) -> Option<&Trie> {
-            let alpha = ch.to_digit(36).unwrap();
+            let alpha = ch.to_digit(36)?;
             self.children[alpha as usize].as_deref()
         }
     }
 
-    const DIGITS: &[(&str, u32)] = &[
-        ("one", 1),
-        ("two", 2),
-        ("three", 3),
-        ("four", 4),
-        ("five", 5),
-        ("six", 6),
-        ("seven", 7),
-        ("eight", 8),
-        ("nine", 9),
-    ];
-
     let mut trie = Trie::default();
     let mut rev_trie = Trie::default();
 
-    for &(name, value) in DIGITS {
+    for (name, value) in ENGLISH.digits(false) {
         trie.insert(name.chars(), value);
         rev_trie.insert(name.chars().rev(), value);
     }
@@ -106,3 +171,113 @@ pub fn part2(input: &str) -> u32 {
         })
         .sum()
 }
+
+#[aoc_runner_derive::aoc(day1, part2, AhoCorasick)]
+pub fn part2_aho_corasick(input: &str) -> u32 { solve_with_language(input, &ENGLISH, false) }
+
+/// Sums the calibration values, reading both numerals and the spelled-out digits of `language`.
+///
+/// Matching is done on UTF-8 bytes, so any input is accepted,
+/// and non-ASCII words cannot match in the middle of another character.
+/// If one word contains another, the digit whose word starts first is read.
+pub fn solve_with_language(input: &str, language: &Language, zero: bool) -> u32 {
+    // numerals are patterns too, so a single automaton finds the first digit of either form
+    let patterns = || language.digits(zero).chain(NUMERALS.into_iter().zip(0..));
+
+    let forward = AhoCorasick::new(patterns());
+    let reverse = AhoCorasick::new_reversed(patterns());
+
+    input
+        .lines()
+        .map(|line| {
+            let first = forward.find_first(line.bytes()).expect("no digit in line");
+            let last = reverse.find_first(line.bytes().rev()).expect("no digit in line");
+            first * 10 + last
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    const SAMPLE_1: &str = r"1abc2
+pqr3stu8vwx
+a1b2c3d4e5f
+treb7uchet";
+
+    const SAMPLE_2: &str = r"two1nine
+eightwothree
+abcone2threexyz
+xtwone3four
+4nineeightseven2
+zoneight234
+7pqrstsixteen";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(super::part1(SAMPLE_1), 142);
+    }
+
+    #[test]
+    fn test_part1_simd() {
+        assert_eq!(super::part1_simd(SAMPLE_1), 142);
+
+        // long enough to span several chunks, with digits straddling a chunk boundary
+        let long = format!("{}7\n8{}\n{SAMPLE_1}\n", "x".repeat(63), "é".repeat(40));
+        assert_eq!(super::part1_simd(&long), super::part1(&long));
+        assert_eq!(super::part1_simd(&long), 77 + 88 + 142);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(super::part2(SAMPLE_2), 281);
+    }
+
+    #[test]
+    fn test_part2_aho_corasick() {
+        assert_eq!(super::part2_aho_corasick(SAMPLE_2), 281);
+        assert_eq!(super::part2_aho_corasick("eightwo\noneight\nsevenine"), 82 + 18 + 79);
+    }
+
+    #[test]
+    fn test_part2_unicode() {
+        assert_eq!(super::part2("ä-3+é\n½two!"), 33 + 22);
+        assert_eq!(super::part2_aho_corasick("ä-3+é\n½two!"), 33 + 22);
+    }
+
+    #[test]
+    fn test_overlapping_words() {
+        use super::{solve_with_language, ENGLISH, FRENCH, GERMAN};
+
+        assert_eq!(solve_with_language("eightwo", &ENGLISH, false), 82);
+        assert_eq!(solve_with_language("twone", &ENGLISH, false), 21);
+        assert_eq!(solve_with_language("cinquatre", &FRENCH, false), 54);
+        assert_eq!(solve_with_language("huitrois", &FRENCH, false), 83);
+        assert_eq!(solve_with_language("dreins", &GERMAN, false), 31);
+        assert_eq!(solve_with_language("siebeneun", &GERMAN, false), 79);
+    }
+
+    #[test]
+    fn test_nested_words() {
+        use super::{solve_with_language, Language, ENGLISH};
+
+        // "on" is a prefix of "one" and ends first
+        let mut words = ENGLISH.words;
+        words[0] = "on";
+        let language = Language { words };
+        assert_eq!(solve_with_language("xonex", &language, true), 11);
+        assert_eq!(solve_with_language("xon3", &language, true), 3);
+        assert_eq!(solve_with_language("3nonex", &language, true), 31);
+    }
+
+    #[test]
+    fn test_zero() {
+        use super::{solve_with_language, ENGLISH, FRENCH, GERMAN};
+
+        assert_eq!(solve_with_language("zerone", &ENGLISH, false), 11);
+        assert_eq!(solve_with_language("zerone", &ENGLISH, true), 1);
+        assert_eq!(solve_with_language("x5zéro", &FRENCH, true), 50);
+        assert_eq!(solve_with_language("x5zéro", &FRENCH, false), 55);
+        assert_eq!(solve_with_language("nullfünf", &GERMAN, true), 5);
+        assert_eq!(solve_with_language("0fünf", &GERMAN, false), 5);
+    }
+}
@@ -49,7 +49,7 @@ impl Pos {
         }
     }
     fn left(&self, grid: &Grid<'_>) -> Option<Self> {
-        if self.0 % grid.width_plus_one == 0 {
+        if self.0.is_multiple_of(grid.width_plus_one) {
             None
         } else {
             Some(Self(self.0 - 1))
@@ -128,11 +128,10 @@ fn identify_loop(input: &str, mut step: impl FnMut(Dir, Pos, Dir)) {
     let mut pos = initial;
     let initial_dir = [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
         .into_iter()
-        .filter(|&dir| match initial.go(&grid, dir) {
+        .find(|&dir| match initial.go(&grid, dir) {
             Some(next) => dir.neg().follow_char(grid.buf[next.into_usize()]).is_some(),
             None => false,
         })
-        .next()
         .unwrap();
 
     let mut next_dir = initial_dir;
@@ -46,10 +46,127 @@ where
 pub fn part1(input: &str) -> u32 { solve(input.as_bytes(), 2) }
 
 #[aoc_runner_derive::aoc(day11, part2)]
-pub fn part2(input: &str) -> u64 { solve(input.as_bytes(), 1000_000) }
+pub fn part2(input: &str) -> u64 { solve(input.as_bytes(), 1_000_000) }
+
+/// The width that each empty column and each empty row expands to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expansion {
+    pub columns: u64,
+    pub rows:    u64,
+}
+
+impl Expansion {
+    pub fn uniform(factor: u64) -> Self { Self { columns: factor, rows: factor } }
+}
+
+pub struct Image {
+    /// `(x, y)` of each galaxy before expansion, in reading order
+    pub galaxies:         Vec<(usize, usize)>,
+    /// number of galaxies in each column
+    columns:              Vec<u64>,
+    /// number of galaxies in each row
+    rows:                 Vec<u64>,
+    /// number of empty columns left of each column
+    empty_columns_before: Vec<u64>,
+    /// number of empty rows above each row
+    empty_rows_before:    Vec<u64>,
+}
+
+fn count_empty_before(stats: &[u64]) -> Vec<u64> {
+    stats
+        .iter()
+        .scan(0, |empty, &stat| {
+            let before = *empty;
+            *empty += u64::from(stat == 0);
+            Some(before)
+        })
+        .collect()
+}
+
+/// The coordinate after each of the `empty_before` empty lines is widened to `factor`.
+fn expand(pos: usize, empty_before: u64, factor: u64) -> u64 {
+    pos as u64 + empty_before * factor - empty_before
+}
+
+impl Image {
+    pub fn parse(input: &str) -> Self {
+        let width = input.find('\n').unwrap_or(input.len());
+        let mut galaxies = Vec::new();
+        let mut columns = vec![0; width];
+        let mut rows = Vec::new();
+
+        for (y, line) in input.lines().enumerate() {
+            rows.push(0);
+            for (x, _) in line.match_indices('#') {
+                galaxies.push((x, y));
+                columns[x] += 1;
+                rows[y] += 1;
+            }
+        }
+
+        Self {
+            galaxies,
+            empty_columns_before: count_empty_before(&columns),
+            empty_rows_before: count_empty_before(&rows),
+            columns,
+            rows,
+        }
+    }
+
+    /// Coordinates of the galaxy with index `index` after expansion.
+    pub fn position(&self, index: usize, expansion: Expansion) -> (u64, u64) {
+        let (x, y) = self.galaxies[index];
+        (
+            expand(x, self.empty_columns_before[x], expansion.columns),
+            expand(y, self.empty_rows_before[y], expansion.rows),
+        )
+    }
+
+    /// Coordinates of all galaxies after expansion.
+    pub fn expanded(&self, expansion: Expansion) -> impl Iterator<Item = (u64, u64)> + '_ {
+        (0..self.galaxies.len()).map(move |index| self.position(index, expansion))
+    }
+
+    /// Manhattan distance between the galaxies with indices `a` and `b` after expansion.
+    pub fn distance(&self, a: usize, b: usize, expansion: Expansion) -> u64 {
+        let ((ax, ay), (bx, by)) = (self.position(a, expansion), self.position(b, expansion));
+        ax.abs_diff(bx) + ay.abs_diff(by)
+    }
+
+    /// Sum of distances between all pairs of galaxies after expansion.
+    pub fn distance_sum(&self, expansion: Expansion) -> u64 {
+        add_dist(&self.columns, expansion.columns) + add_dist(&self.rows, expansion.rows)
+    }
+
+    /// Sums of distances for each expansion.
+    ///
+    /// Each axis contributes `base + (factor - 1) * slope`,
+    /// so only two evaluations per axis are needed regardless of the number of expansions.
+    pub fn distance_sums(&self, expansions: &[Expansion]) -> Vec<u64> {
+        let linear = |stats: &[u64]| {
+            let base = add_dist(stats, 1);
+            let slope = add_dist(stats, 2) - base;
+            move |factor: u64| base + factor * slope - slope
+        };
+        let columns = linear(&self.columns);
+        let rows = linear(&self.rows);
+
+        expansions
+            .iter()
+            .map(|expansion| columns(expansion.columns) + rows(expansion.rows))
+            .collect()
+    }
+}
+
+#[aoc_runner_derive::aoc(day11, part2, Image)]
+pub fn part2_image(input: &str) -> u64 {
+    Image::parse(input).distance_sum(Expansion::uniform(1_000_000))
+}
 
 #[cfg(test)]
 mod tests {
+    use super::{Expansion, Image};
+
     const SAMPLE: &str = "...#......
 .......#..
 #.........
@@ -71,4 +188,53 @@ mod tests {
     fn test_part2() {
         assert_eq!(super::solve(SAMPLE.as_bytes(), 100), 8410);
     }
+
+    #[test]
+    fn test_image() {
+        let image = Image::parse(SAMPLE);
+        assert_eq!(image.galaxies.len(), 9);
+        assert_eq!(image.distance_sum(Expansion::uniform(2)), 374);
+        assert_eq!(image.distance_sum(Expansion::uniform(10)), 1030);
+        assert_eq!(super::part2_image(SAMPLE), super::part2(SAMPLE));
+
+        let sums = image.distance_sums(&[2, 10, 100, 1].map(Expansion::uniform));
+        assert_eq!(sums, [374, 1030, 8410, image.distance_sum(Expansion::uniform(1))]);
+    }
+
+    #[test]
+    fn test_pair_distances() {
+        let image = Image::parse(SAMPLE);
+        let double = Expansion::uniform(2);
+        // galaxies are numbered from 1 in the puzzle
+        assert_eq!(image.distance(4, 8, double), 9);
+        assert_eq!(image.distance(0, 6, double), 15);
+        assert_eq!(image.distance(2, 5, double), 17);
+        assert_eq!(image.distance(7, 8, double), 5);
+
+        let expanded: Vec<_> = image.expanded(double).collect();
+        assert_eq!(expanded[0], (4, 0));
+        assert_eq!(expanded[8], (5, 11));
+    }
+
+    #[test]
+    fn test_per_axis() {
+        let image = Image::parse(SAMPLE);
+        for expansion in [
+            Expansion { columns: 1, rows: 1 },
+            Expansion { columns: 0, rows: 3 },
+            Expansion { columns: 7, rows: 2 },
+            Expansion { columns: 1_000_000, rows: 5 },
+        ] {
+            let expanded: Vec<_> = image.expanded(expansion).collect();
+            let mut brute_force = 0;
+            for (i, &(ax, ay)) in expanded.iter().enumerate() {
+                for &(bx, by) in &expanded[..i] {
+                    brute_force += ax.abs_diff(bx) + ay.abs_diff(by);
+                }
+            }
+
+            assert_eq!(image.distance_sum(expansion), brute_force);
+            assert_eq!(image.distance_sums(&[expansion]), [brute_force]);
+        }
+    }
 }
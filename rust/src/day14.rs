@@ -1,4 +1,8 @@
 use std::collections::{hash_map, HashMap};
+use std::iter;
+
+use bitvec::order::LocalBits;
+use bitvec::vec::BitVec;
 
 #[aoc_runner_derive::aoc(day14, part1)]
 pub fn part1(input: &str) -> u32 {
@@ -26,7 +30,7 @@ pub fn part1(input: &str) -> u32 {
 const PART2_TURNS: u32 = 1000000000;
 
 fn tilt_vert(
-    gauges: &mut Vec<u32>,
+    gauges: &mut [u32],
     map: &mut [u8],
     width: u32,
     line_order: impl Iterator<Item = usize>,
@@ -84,7 +88,7 @@ fn compute_load<'t>(lines: impl DoubleEndedIterator<Item = &'t [u8]>) -> u32 {
     output
 }
 
-#[aoc_runner_derive::aoc(day14, part2)]
+#[aoc_runner_derive::aoc(day14, part2, Bytes)]
 pub fn part2(input: &str) -> u32 {
     let width = input.find('\n').unwrap() as u32;
     let full_width = width as usize + 1;
@@ -95,7 +99,7 @@ pub fn part2(input: &str) -> u32 {
     map.reserve_exact(map_len);
     map.resize(map_len, b'\n'); // blame cargo-aoc for this
 
-    let mut gauges = vec![0; height as usize];
+    let mut gauges = vec![0; width as usize];
     let mut snapshot_map = HashMap::new();
     let mut snapshot_list = Vec::new();
 
@@ -114,7 +118,7 @@ pub fn part2(input: &str) -> u32 {
                 let mut remain_turns = PART2_TURNS - cycle_end;
                 remain_turns %= cycle_end - cycle_start;
 
-                let final_map = &snapshot_list[(cycle_start + remain_turns) as usize][..];
+                let final_map = snapshot_list[(cycle_start + remain_turns) as usize];
                 return compute_load(final_map.chunks_exact(full_width));
             }
         }
@@ -150,8 +154,138 @@ pub fn part2(input: &str) -> u32 {
     compute_load(map.chunks_exact(full_width))
 }
 
+type BitRow = BitVec<usize, LocalBits>;
+
+/// Round and cube rocks stored as one bitset per row.
+struct BitGrid {
+    width: usize,
+    round: Vec<BitRow>,
+    cube:  Vec<BitRow>,
+}
+
+impl BitGrid {
+    fn parse(input: &str) -> Self {
+        let width = input.find('\n').unwrap_or(input.len());
+        let mut round = Vec::new();
+        let mut cube = Vec::new();
+        for line in input.lines() {
+            round.push(line.bytes().map(|b| b == b'O').collect());
+            cube.push(line.bytes().map(|b| b == b'#').collect());
+        }
+        Self { width, round, cube }
+    }
+
+    /// Tilts towards `order[0]`, where `order` lists row indices starting from the edge.
+    ///
+    /// Rows closer to the edge are already settled when a row is processed,
+    /// so each word of rocks keeps moving until every bit has been blocked.
+    fn tilt_vert(&mut self, order: &[usize], moving: &mut Vec<usize>) {
+        for (i, &row) in order.iter().enumerate().skip(1) {
+            moving.clear();
+            moving.extend_from_slice(self.round[row].as_raw_slice());
+            if moving.iter().all(|&word| word == 0) {
+                continue;
+            }
+            self.round[row].fill(false);
+
+            let mut current = row;
+            for &next in order[..i].iter().rev() {
+                let [current_row, next_row] = self.round.get_disjoint_mut([current, next]).unwrap();
+                let next_cube = self.cube[next].as_raw_slice();
+
+                let mut any_moving = 0;
+                for ((word, dest), (&round, &cube)) in moving
+                    .iter_mut()
+                    .zip(current_row.as_raw_mut_slice())
+                    .zip(next_row.as_raw_slice().iter().zip(next_cube))
+                {
+                    let blocked = round | cube;
+                    *dest |= *word & blocked;
+                    *word &= !blocked;
+                    any_moving |= *word;
+                }
+
+                if any_moving == 0 {
+                    break;
+                }
+                current = next;
+            }
+
+            // rocks that were never blocked reach the edge
+            for (dest, &word) in self.round[current].as_raw_mut_slice().iter_mut().zip(&*moving) {
+                *dest |= word;
+            }
+        }
+    }
+
+    /// Packs the round rocks of each segment between cube rocks to one end of the row.
+    fn tilt_horiz(&mut self, towards_start: bool) {
+        for (round, cube) in self.round.iter_mut().zip(&self.cube) {
+            let mut seg_start = 0;
+            for seg_end in cube.iter_ones().chain(iter::once(self.width)) {
+                let segment = &mut round[seg_start..seg_end];
+                let count = segment.count_ones();
+                segment.fill(false);
+                if towards_start {
+                    segment[..count].fill(true);
+                } else {
+                    let len = segment.len();
+                    segment[len - count..].fill(true);
+                }
+                seg_start = seg_end + 1;
+            }
+        }
+    }
+
+    fn load(&self) -> u32 {
+        (1..)
+            .zip(self.round.iter().rev())
+            .map(|(factor, row)| row.count_ones() as u32 * factor)
+            .sum()
+    }
+}
+
+#[aoc_runner_derive::aoc(day14, part2, Bitset)]
+pub fn part2_bitset(input: &str) -> u32 {
+    let mut grid = BitGrid::parse(input);
+
+    let north: Vec<usize> = (0..grid.round.len()).collect();
+    let south: Vec<usize> = north.iter().copied().rev().collect();
+    let mut moving = Vec::new();
+
+    let mut snapshot_map = HashMap::new();
+    let mut load_list = Vec::new();
+
+    for turn in 0..PART2_TURNS {
+        load_list.push(grid.load());
+        match snapshot_map.entry(grid.round.clone()) {
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(turn);
+            }
+            hash_map::Entry::Occupied(entry) => {
+                let &cycle_start = entry.get();
+                let cycle_end = turn;
+
+                let mut remain_turns = PART2_TURNS - cycle_end;
+                remain_turns %= cycle_end - cycle_start;
+
+                return load_list[(cycle_start + remain_turns) as usize];
+            }
+        }
+
+        grid.tilt_vert(&north, &mut moving);
+        grid.tilt_horiz(true);
+        grid.tilt_vert(&south, &mut moving);
+        grid.tilt_horiz(false);
+    }
+
+    grid.load()
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::test_util::Xorshift;
+
     const SAMPLE: &str = r"O....#....
 O.OO#....#
 .....##...
@@ -173,4 +307,35 @@ O.#..O.#.#
     fn test_part2() {
         assert_eq!(super::part2(SAMPLE), 64);
     }
+
+    #[test]
+    fn test_part2_wide() {
+        // without cube rocks, every rock settles in the bottom row
+        assert_eq!(super::part2("O...O\n.O...\n"), 3);
+    }
+
+    #[test]
+    fn test_part2_bitset() {
+        assert_eq!(super::part2_bitset(SAMPLE), 64);
+    }
+
+    #[test]
+    fn test_part2_bitset_matches_bytes() {
+        // include grids wider than a word
+        let mut rng = Xorshift::new(0x2545_f491_4f6c_dd1d);
+        for (width, height) in [(10, 10), (70, 13), (131, 37)] {
+            let mut input = String::new();
+            for _ in 0..height {
+                for _ in 0..width {
+                    input.push(match rng.below(8) {
+                        0 => '#',
+                        1 | 2 => 'O',
+                        _ => '.',
+                    });
+                }
+                input.push('\n');
+            }
+            assert_eq!(super::part2_bitset(&input), super::part2(&input), "{input}");
+        }
+    }
 }
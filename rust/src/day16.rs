@@ -17,7 +17,7 @@ impl Direction {
 
     fn follow(self, from: usize, width: usize, full_len: usize) -> Option<usize> {
         match self {
-            Self::Left if from % width != 0 => Some(from - 1),
+            Self::Left if !from.is_multiple_of(width) => Some(from - 1),
             Self::Right if from % width != width - 2 => Some(from + 1),
             Self::Up => from.checked_sub(width),
             Self::Down if from + width < full_len => Some(from + width),
@@ -1,5 +1,4 @@
 use std::collections::BinaryHeap;
-use std::num::NonZeroU8;
 use std::{array, cmp, fmt};
 
 macro_rules! log {
@@ -56,7 +55,7 @@ impl<'t> Grid<'t> {
         Some(Pos(match dir {
             Dir::Up => pos.0.checked_sub(self.width)?,
             Dir::Down if pos.0 + self.width < self.input.len() as u32 => pos.0 + self.width,
-            Dir::Left if pos.0 % self.width != 0 => pos.0 - 1,
+            Dir::Left if !pos.0.is_multiple_of(self.width) => pos.0 - 1,
             Dir::Right if pos.0 % self.width != self.width - 2 => pos.0 + 1,
             _ => return None,
         }))
@@ -222,7 +221,7 @@ pub fn part2(input: &str) -> u32 {
                 admit.prev_dir_steps >= 4
             }
         },
-        |prev_state, next_steps, dir| {
+        |prev_state, next_steps, _dir| {
             if next_steps > 1 {
                 return true;
             }
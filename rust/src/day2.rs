@@ -1,16 +1,30 @@
-use std::ops;
+use std::collections::BTreeMap;
+use std::{fmt, ops};
 
-fn parse_line(mut line: &str) -> (u32, impl Iterator<Item = Set> + '_) {
+/// Splits a line into the game ID and, for each draw, its `(count, color)` terms.
+fn parse_terms(
+    mut line: &str,
+) -> (u32, impl Iterator<Item = impl Iterator<Item = (u32, &str)> + '_> + '_) {
     line = line.strip_prefix("Game ").expect("start with Game");
 
     let (id_str, sets) = line.split_once(':').expect("has colon");
     let id = id_str.parse::<u32>().unwrap();
 
     let sets = sets.split(';').map(|set| {
-        set.split(',')
-            .map(|mut term| {
-                term = term.strip_prefix(' ').expect("space before number");
-                let (num, color_str) = term.split_once(' ').expect("has space");
+        set.split(',').map(|mut term| {
+            term = term.strip_prefix(' ').expect("space before number");
+            let (num, color) = term.split_once(' ').expect("has space");
+            (num.parse::<u32>().unwrap(), color)
+        })
+    });
+    (id, sets)
+}
+
+fn parse_line(line: &str) -> (u32, impl Iterator<Item = Set> + '_) {
+    let (id, sets) = parse_terms(line);
+    let sets = sets.map(|terms| {
+        terms
+            .map(|(num, color_str)| {
                 let mut subset = Set::default();
                 let color = match color_str {
                     "red" => &mut subset.r,
@@ -18,7 +32,7 @@ fn parse_line(mut line: &str) -> (u32, impl Iterator<Item = Set> + '_) {
                     "blue" => &mut subset.b,
                     _ => unreachable!(),
                 };
-                *color += num.parse::<u32>().unwrap();
+                *color += num;
                 subset
             })
             .fold(Set::default(), |a, b| a + b)
@@ -44,7 +58,7 @@ impl ops::Add for Set {
     }
 }
 
-#[aoc_runner_derive::aoc(day2, part1)]
+#[aoc_runner_derive::aoc(day2, part1, Set)]
 pub fn part1(input: &str) -> u32 {
     input
         .lines()
@@ -55,7 +69,7 @@ pub fn part1(input: &str) -> u32 {
         .sum()
 }
 
-#[aoc_runner_derive::aoc(day2, part2)]
+#[aoc_runner_derive::aoc(day2, part2, Set)]
 pub fn part2(input: &str) -> u32 {
     input
         .lines()
@@ -68,8 +82,256 @@ pub fn part2(input: &str) -> u32 {
         .sum()
 }
 
+/// Cube counts by color name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bag<'t>(pub BTreeMap<&'t str, u32>);
+
+impl<'t> Bag<'t> {
+    /// The limits of part 1.
+    pub fn part1_limits() -> Self { Self::from_iter([("red", 12), ("green", 13), ("blue", 14)]) }
+
+    pub fn get(&self, color: &str) -> u32 { self.0.get(color).copied().unwrap_or(0) }
+
+    pub fn add(&mut self, color: &'t str, count: u32) {
+        *self.0.entry(color).or_default() += count;
+    }
+
+    /// Colorwise maximum of the two bags.
+    pub fn max(mut self, other: &Self) -> Self {
+        for (&color, &count) in &other.0 {
+            let entry = self.0.entry(color).or_default();
+            *entry = (*entry).max(count);
+        }
+        self
+    }
+
+    /// The first color (by name) of which there are more cubes than in `limits`.
+    /// Colors absent from `limits` have a limit of 0.
+    pub fn exceeding(&self, limits: &Bag) -> Option<(&'t str, u32)> {
+        self.0
+            .iter()
+            .find(|&(color, &count)| count > limits.get(color))
+            .map(|(&color, &count)| (color, count))
+    }
+
+    /// Product of the counts of all colors in the bag.
+    pub fn power(&self) -> u32 { self.0.values().product() }
+}
+
+impl<'t> FromIterator<(&'t str, u32)> for Bag<'t> {
+    fn from_iter<I: IntoIterator<Item = (&'t str, u32)>>(iter: I) -> Self {
+        let mut bag = Self::default();
+        for (color, count) in iter {
+            bag.add(color, count);
+        }
+        bag
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game<'t> {
+    pub id:    u32,
+    pub draws: Vec<Bag<'t>>,
+}
+
+impl<'t> Game<'t> {
+    pub fn parse(line: &'t str) -> Self {
+        let (id, draws) = parse_terms(line);
+        let draws =
+            draws.map(|terms| terms.map(|(count, color)| (color, count)).collect()).collect();
+        Self { id, draws }
+    }
+
+    /// Checks that every draw could have been taken from a bag with `limits`.
+    pub fn check(&self, limits: &Bag) -> Result<(), Violation<'t>> {
+        for (draw, bag) in self.draws.iter().enumerate() {
+            if let Some((color, count)) = bag.exceeding(limits) {
+                return Err(Violation { draw, color, count, limit: limits.get(color) });
+            }
+        }
+        Ok(())
+    }
+
+    /// The smallest bag from which every draw could have been taken.
+    pub fn minimum_bag(&self) -> Bag<'t> { self.draws.iter().fold(Bag::default(), Bag::max) }
+}
+
+/// The first draw of a game that does not fit in the bag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation<'t> {
+    /// 0-based index of the draw in the game
+    pub draw:  usize,
+    pub color: &'t str,
+    pub count: u32,
+    pub limit: u32,
+}
+
+impl fmt::Display for Violation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "draw {} has {} {} cubes, but the bag only has {}",
+            self.draw + 1,
+            self.count,
+            self.color,
+            self.limit
+        )
+    }
+}
+
+/// Checks every game of the input against `limits`.
+pub fn feasibility<'t>(input: &'t str, limits: &Bag) -> Vec<(u32, Result<(), Violation<'t>>)> {
+    input
+        .lines()
+        .map(|line| {
+            let game = Game::parse(line);
+            (game.id, game.check(limits))
+        })
+        .collect()
+}
+
+#[aoc_runner_derive::aoc(day2, part1, Bag)]
+pub fn part1_bag(input: &str) -> u32 {
+    feasibility(input, &Bag::part1_limits())
+        .into_iter()
+        .filter_map(|(id, result)| result.is_ok().then_some(id))
+        .sum()
+}
+
+#[aoc_runner_derive::aoc(day2, part2, Bag)]
+pub fn part2_bag(input: &str) -> u32 {
+    // a color never drawn in a game still counts as a zero factor
+    let rgb = Bag::from_iter([("red", 0), ("green", 0), ("blue", 0)]);
+    input.lines().map(|line| rgb.clone().max(&Game::parse(line).minimum_bag()).power()).sum()
+}
+
+/// One `count color` term of a draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event<'t> {
+    pub game:  u32,
+    /// 0-based index of the draw in the game
+    pub draw:  u32,
+    pub color: &'t str,
+    pub count: u32,
+}
+
+/// Single-pass byte-level parser yielding every term of the input without allocating.
+pub struct Events<'t> {
+    input: &'t str,
+    pos:   usize,
+    game:  u32,
+    draw:  u32,
+}
+
+impl<'t> Events<'t> {
+    pub fn new(input: &'t str) -> Self { Self { input, pos: 0, game: 0, draw: 0 } }
+
+    fn number(&mut self) -> u32 {
+        let mut value = 0;
+        while let Some(&byte) = self.input.as_bytes().get(self.pos).filter(|b| b.is_ascii_digit()) {
+            value = value * 10 + u32::from(byte - b'0');
+            self.pos += 1;
+        }
+        value
+    }
+}
+
+impl<'t> Iterator for Events<'t> {
+    type Item = Event<'t>;
+
+    fn next(&mut self) -> Option<Event<'t>> {
+        let bytes = self.input.as_bytes();
+        loop {
+            match *bytes.get(self.pos)? {
+                b'G' => {
+                    self.pos += "Game ".len();
+                    self.game = self.number();
+                    self.draw = 0;
+                }
+                b';' => {
+                    self.draw += 1;
+                    self.pos += 1;
+                }
+                b'0'..=b'9' => {
+                    let count = self.number();
+                    let start = self.pos + 1; // skip the space
+                    let len = bytes[start..]
+                        .iter()
+                        .position(|b| matches!(b, b',' | b';' | b'\n'))
+                        .unwrap_or(bytes.len() - start);
+                    self.pos = start + len;
+                    // delimiters are ASCII, so the slice is on char boundaries
+                    let color = &self.input[start..self.pos];
+                    return Some(Event { game: self.game, draw: self.draw, color, count });
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+}
+
+/// Calls `on_draw` with the game ID and the sum of each draw, in input order.
+fn stream_draws(input: &str, mut on_draw: impl FnMut(u32, u32, &Set)) {
+    let mut current: Option<(u32, u32)> = None;
+    let mut set = Set::default();
+
+    for event in Events::new(input) {
+        if current.is_some_and(|key| key != (event.game, event.draw)) {
+            let (game, draw) = current.unwrap();
+            on_draw(game, draw, &set);
+            set = Set::default();
+        }
+        current = Some((event.game, event.draw));
+
+        *match event.color {
+            "red" => &mut set.r,
+            "green" => &mut set.g,
+            "blue" => &mut set.b,
+            _ => unreachable!(),
+        } += event.count;
+    }
+
+    if let Some((game, draw)) = current {
+        on_draw(game, draw, &set);
+    }
+}
+
+#[aoc_runner_derive::aoc(day2, part1, Stream)]
+pub fn part1_stream(input: &str) -> u32 {
+    let mut sum = 0;
+    let mut possible = false;
+    stream_draws(input, |game, draw, set| {
+        if draw == 0 {
+            possible = true;
+            sum += game;
+        }
+        if possible && !(set.r <= 12 && set.g <= 13 && set.b <= 14) {
+            possible = false;
+            sum -= game;
+        }
+    });
+    sum
+}
+
+#[aoc_runner_derive::aoc(day2, part2, Stream)]
+pub fn part2_stream(input: &str) -> u32 {
+    let mut sum = 0;
+    let mut min = Set::default();
+    stream_draws(input, |_, draw, set| {
+        if draw == 0 {
+            sum += min.power();
+            min = Set::default();
+        }
+        min = Set { r: min.r.max(set.r), g: min.g.max(set.g), b: min.b.max(set.b) };
+    });
+    sum + min.power()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::Event;
+    use crate::test_util::Xorshift;
+
     const SAMPLE: &str = r"Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
 Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
 Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
@@ -85,4 +347,95 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
     fn test_part2() {
         assert_eq!(super::part2(SAMPLE), 2286);
     }
+
+    #[test]
+    fn test_bag_variants() {
+        assert_eq!(super::part1_bag(SAMPLE), 8);
+        assert_eq!(super::part2_bag(SAMPLE), 2286);
+    }
+
+    #[test]
+    fn test_stream_variants() {
+        assert_eq!(super::part1_stream(SAMPLE), 8);
+        assert_eq!(super::part2_stream(SAMPLE), 2286);
+    }
+
+    fn random_input(rng: &mut Xorshift) -> String {
+        let mut input = String::new();
+        for game in 1..=rng.below(30) + 1 {
+            input += &format!("Game {game}:");
+            for draw in 0..rng.below(6) + 1 {
+                if draw > 0 {
+                    input.push(';');
+                }
+                for term in 0..rng.below(3) + 1 {
+                    if term > 0 {
+                        input.push(',');
+                    }
+                    let color = ["red", "green", "blue"][rng.below(3) as usize];
+                    input += &format!(" {} {color}", rng.below(20));
+                }
+            }
+            input.push('\n');
+        }
+        input
+    }
+
+    #[test]
+    fn test_events_match_parse_terms() {
+        let mut rng = Xorshift::new(2);
+        for _ in 0..100 {
+            let mut input = random_input(&mut rng);
+            if rng.below(2) == 0 {
+                input.pop();
+            }
+
+            let expect: Vec<_> = input
+                .lines()
+                .flat_map(|line| {
+                    let (game, draws) = super::parse_terms(line);
+                    draws
+                        .zip(0..)
+                        .flat_map(|(terms, draw)| {
+                            terms.map(move |(count, color)| Event { game, draw, color, count })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            assert_eq!(super::Events::new(&input).collect::<Vec<_>>(), expect);
+
+            assert_eq!(super::part1_stream(&input), super::part1(&input));
+            assert_eq!(super::part2_stream(&input), super::part2(&input));
+        }
+    }
+
+    #[test]
+    fn test_feasibility() {
+        use super::{feasibility, Bag, Violation};
+
+        let report = feasibility(SAMPLE, &Bag::part1_limits());
+        assert_eq!(report[0], (1, Ok(())));
+        assert_eq!(report[2], (3, Err(Violation { draw: 0, color: "red", count: 20, limit: 12 })));
+        assert_eq!(report[3], (4, Err(Violation { draw: 2, color: "blue", count: 15, limit: 14 })));
+        assert_eq!(
+            report[3].1.as_ref().unwrap_err().to_string(),
+            "draw 3 has 15 blue cubes, but the bag only has 14"
+        );
+    }
+
+    #[test]
+    fn test_arbitrary_colors() {
+        use super::{Bag, Game, Violation};
+
+        let game = Game::parse("Game 7: 2 mauve, 1 teal; 3 teal, 1 mauve, 1 red; 4 teal");
+        assert_eq!(game.minimum_bag(), Bag::from_iter([("mauve", 2), ("teal", 4), ("red", 1)]));
+        assert_eq!(game.minimum_bag().power(), 8);
+
+        let limits = Bag::from_iter([("mauve", 2), ("teal", 5)]);
+        assert_eq!(
+            game.check(&limits),
+            Err(Violation { draw: 1, color: "red", count: 1, limit: 0 })
+        );
+        assert_eq!(game.check(&limits.max(&Bag::from_iter([("red", 1)]))), Ok(()));
+    }
 }
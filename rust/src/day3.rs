@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
 use std::ops;
 
 use bitvec::vec::BitVec;
@@ -9,15 +11,22 @@ struct Matrix<'t> {
 
 impl<'t> Matrix<'t> {
     fn new(data: &'t str) -> Self {
-        Self { line_len: data.find('\n').unwrap() + 1, data: data.as_bytes() }
+        // a single line without trailing newline is still a valid grid
+        let width = data.find('\n').unwrap_or(data.len());
+        Self { line_len: width + 1, data: data.as_bytes() }
     }
 
-    fn has_x(&self, x: i16) -> bool { (0..self.line_len as i16 - 1).contains(&x) }
+    fn width(&self) -> usize { self.line_len - 1 }
 
-    fn has_y(&self, y: i16) -> bool { (0..=(self.data.len() / self.line_len) as i16).contains(&y) }
+    /// Number of rows, whether or not the last line ends with a newline.
+    fn height(&self) -> usize { self.data.len().div_ceil(self.line_len) }
+
+    fn has_x(&self, x: i16) -> bool { (0..self.width() as i16).contains(&x) }
+
+    fn has_y(&self, y: i16) -> bool { (0..self.height() as i16).contains(&y) }
 
     fn to_index(&self, coord: Vec2) -> Option<usize> {
-        if self.has_x(coord.1) && self.has_y(coord.1) {
+        if self.has_x(coord.0) && self.has_y(coord.1) {
             Some(coord.0 as usize + self.line_len * coord.1 as usize)
         } else {
             None
@@ -27,9 +36,40 @@ impl<'t> Matrix<'t> {
     fn to_coord(&self, index: usize) -> Vec2 {
         Vec2((index % self.line_len) as i16, (index / self.line_len) as i16)
     }
+
+    fn get(&self, coord: Vec2) -> Option<u8> { self.to_index(coord).map(|index| self.data[index]) }
+
+    /// The in-bounds cells among the 8 surrounding `center`.
+    fn neighbours(&self, center: Vec2) -> impl Iterator<Item = Vec2> + '_ {
+        (-1..=1)
+            .flat_map(move |dy| (-1..=1).map(move |dx| Vec2(center.0 + dx, center.1 + dy)))
+            .filter(move |&coord| coord != center && self.to_index(coord).is_some())
+    }
+
+    /// Index ranges of the rows of the 3×3 window around `center`, clipped to the grid.
+    fn window_rows(&self, center: Vec2) -> impl Iterator<Item = ops::RangeInclusive<usize>> + '_ {
+        let x_start = if self.has_x(center.0 - 1) { center.0 - 1 } else { center.0 };
+        let x_end = if self.has_x(center.0 + 1) { center.0 + 1 } else { center.0 };
+        (center.1 - 1..=center.1 + 1).filter_map(move |y| {
+            let start = self.to_index(Vec2(x_start, y))?;
+            Some(start..=start + (x_end - x_start) as usize)
+        })
+    }
+
+    /// The number with a digit at `coord`, keyed by the coordinate of its first digit.
+    fn number_at(&self, coord: Vec2) -> Option<(Vec2, u32)> {
+        let digit = |x| self.get(Vec2(x, coord.1)).filter(u8::is_ascii_digit);
+        digit(coord.0)?;
+
+        let start =
+            (0..coord.0).rev().take_while(|&x| digit(x).is_some()).last().unwrap_or(coord.0);
+        let value =
+            (start..).map_while(digit).fold(0, |value, byte| value * 10 + u32::from(byte - b'0'));
+        Some((Vec2(start, coord.1), value))
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Vec2(i16, i16);
 
 struct IterNumbers<'t> {
@@ -41,9 +81,7 @@ impl<'t> Iterator for IterNumbers<'t> {
     type Item = (ops::Range<usize>, u32);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(noise_len) = self.scan.find(|ch: char| ch.is_ascii_digit()) else {
-            return None;
-        };
+        let noise_len = self.scan.find(|ch: char| ch.is_ascii_digit())?;
 
         self.scan = &self.scan[noise_len..];
         self.offset += noise_len;
@@ -70,20 +108,8 @@ pub fn part1(input: &str) -> u32 {
 
     input.match_indices(|ch: char| !ch.is_ascii_digit() && ch != '.' && ch != '\n').for_each(
         |(index, _)| {
-            let vec = mat.to_coord(index);
-            let (mut x_start, mut x_end) = (vec.0 - 1, vec.0 + 1);
-            if !mat.has_x(x_start) {
-                x_start += 1;
-            }
-            if !mat.has_x(x_end) {
-                x_end -= 1;
-            }
-            for y in (vec.1 - 1)..=(vec.1 + 1) {
-                if mat.has_y(y) {
-                    let start_index = mat.to_index(Vec2(x_start, y)).unwrap();
-                    let end_index = start_index + (x_end - x_start) as usize;
-                    active[start_index..=end_index].fill(true);
-                }
+            for range in mat.window_rows(mat.to_coord(index)) {
+                active[range].fill(true);
             }
         },
     );
@@ -175,8 +201,216 @@ pub fn part2(input: &str) -> u32 {
     sum
 }
 
+#[aoc_runner_derive::aoc(day3, part2, Neighbours)]
+pub fn part2_neighbours(input: &str) -> u32 {
+    let mat = Matrix::new(input);
+
+    let mut adjs = Vec::with_capacity(8);
+    let mut sum = 0;
+
+    for (gear_index, _) in input.match_indices('*') {
+        adjs.clear();
+        for coord in mat.neighbours(mat.to_coord(gear_index)) {
+            if let Some(number) = mat.number_at(coord) {
+                if !adjs.contains(&number) {
+                    adjs.push(number);
+                }
+            }
+        }
+
+        if let [(_, a), (_, b)] = adjs[..] {
+            sum += a * b;
+        }
+    }
+    sum
+}
+
+/// Sums the part numbers of `cur`, given the rows above and below it.
+fn sum_row(prev: &[u8], cur: &[u8], next: &[u8]) -> u32 {
+    let is_symbol = |byte: &u8| !byte.is_ascii_digit() && *byte != b'.';
+
+    let mut sum = 0;
+    let mut x = 0;
+    while x < cur.len() {
+        if !cur[x].is_ascii_digit() {
+            x += 1;
+            continue;
+        }
+
+        let start = x;
+        let mut value = 0;
+        while let Some(&byte) = cur.get(x).filter(|b| b.is_ascii_digit()) {
+            value = value * 10 + u32::from(byte - b'0');
+            x += 1;
+        }
+
+        let window = start.saturating_sub(1)..x + 1;
+        if [prev, cur, next].iter().any(|row| {
+            row[window.start.min(row.len())..window.end.min(row.len())].iter().any(is_symbol)
+        }) {
+            sum += value;
+        }
+    }
+    sum
+}
+
+/// Sums the part numbers while reading the schematic line by line,
+/// keeping only the current row and its two neighbours in memory.
+pub fn sum_part_numbers(mut reader: impl BufRead) -> io::Result<u32> {
+    // previous, current and next row
+    let mut rows: [Vec<u8>; 3] = Default::default();
+    let mut has_current = false;
+    let mut sum = 0;
+
+    loop {
+        rows[2].clear();
+        let read = reader.read_until(b'\n', &mut rows[2])?;
+        if rows[2].last() == Some(&b'\n') {
+            rows[2].pop();
+        }
+
+        if has_current {
+            sum += sum_row(&rows[0], &rows[1], &rows[2]);
+        }
+        if read == 0 {
+            return Ok(sum);
+        }
+
+        rows.rotate_left(1);
+        has_current = true;
+    }
+}
+
+#[aoc_runner_derive::aoc(day3, part1, Stream)]
+pub fn part1_stream(input: &str) -> u32 { sum_part_numbers(input.as_bytes()).unwrap() }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number {
+    /// column of the first digit
+    pub x:     usize,
+    pub y:     usize,
+    pub len:   usize,
+    pub value: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub x:  usize,
+    pub y:  usize,
+    pub ch: char,
+}
+
+/// Bipartite adjacency graph between the numbers and the symbols of a schematic.
+pub struct Schematic {
+    pub numbers:    Vec<Number>,
+    pub symbols:    Vec<Symbol>,
+    /// indices into `symbols` for each number
+    number_symbols: Vec<Vec<usize>>,
+    /// indices into `numbers` for each symbol
+    symbol_numbers: Vec<Vec<usize>>,
+}
+
+impl Schematic {
+    pub fn parse(input: &str) -> Self {
+        let mat = Matrix::new(input);
+
+        let mut numbers = Vec::new();
+        let mut number_at_index = HashMap::new();
+        for (range, value) in iter_numbers(input) {
+            let start = mat.to_coord(range.start);
+            number_at_index.insert(range.start, numbers.len());
+            numbers.push(Number {
+                x: start.0 as usize,
+                y: start.1 as usize,
+                len: range.len(),
+                value,
+            });
+        }
+
+        let mut symbols = Vec::new();
+        let mut number_symbols = vec![Vec::new(); numbers.len()];
+        let mut symbol_numbers = Vec::new();
+        for (index, ch) in
+            input.match_indices(|ch: char| !ch.is_ascii_digit() && ch != '.' && ch != '\n')
+        {
+            let coord = mat.to_coord(index);
+            let symbol = symbols.len();
+            symbols.push(Symbol {
+                x:  coord.0 as usize,
+                y:  coord.1 as usize,
+                ch: ch.chars().next().unwrap(),
+            });
+
+            let mut adjs: Vec<usize> = Vec::new();
+            for neighbour in mat.neighbours(coord) {
+                if let Some((start, _)) = mat.number_at(neighbour) {
+                    let number = number_at_index[&mat.to_index(start).unwrap()];
+                    if !adjs.contains(&number) {
+                        adjs.push(number);
+                        number_symbols[number].push(symbol);
+                    }
+                }
+            }
+            symbol_numbers.push(adjs);
+        }
+
+        Self { numbers, symbols, number_symbols, symbol_numbers }
+    }
+
+    pub fn symbols_of(&self, number: usize) -> impl Iterator<Item = &Symbol> + '_ {
+        self.number_symbols[number].iter().map(|&symbol| &self.symbols[symbol])
+    }
+
+    pub fn numbers_of(&self, symbol: usize) -> impl Iterator<Item = &Number> + '_ {
+        self.symbol_numbers[symbol].iter().map(|&number| &self.numbers[number])
+    }
+
+    /// Numbers adjacent to at least one symbol.
+    pub fn part_numbers(&self) -> impl Iterator<Item = &Number> + '_ {
+        self.numbers_where(|_| true)
+    }
+
+    /// Numbers adjacent to at least one `ch` symbol.
+    pub fn numbers_adjacent_to(&self, ch: char) -> impl Iterator<Item = &Number> + '_ {
+        self.numbers_where(move |symbol| symbol.ch == ch)
+    }
+
+    fn numbers_where<'t>(
+        &'t self,
+        pred: impl Fn(&Symbol) -> bool + 't,
+    ) -> impl Iterator<Item = &'t Number> + 't {
+        self.numbers
+            .iter()
+            .zip(&self.number_symbols)
+            .filter(move |(_, symbols)| symbols.iter().any(|&symbol| pred(&self.symbols[symbol])))
+            .map(|(number, _)| number)
+    }
+
+    /// Indices of the symbols adjacent to exactly `degree` numbers.
+    pub fn symbols_with_degree(&self, degree: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.symbols.len()).filter(move |&symbol| self.symbol_numbers[symbol].len() == degree)
+    }
+}
+
+#[aoc_runner_derive::aoc(day3, part1, Graph)]
+pub fn part1_graph(input: &str) -> u32 {
+    Schematic::parse(input).part_numbers().map(|number| number.value).sum()
+}
+
+#[aoc_runner_derive::aoc(day3, part2, Graph)]
+pub fn part2_graph(input: &str) -> u32 {
+    let schematic = Schematic::parse(input);
+    schematic
+        .symbols_with_degree(2)
+        .filter(|&symbol| schematic.symbols[symbol].ch == '*')
+        .map(|symbol| schematic.numbers_of(symbol).map(|number| number.value).product::<u32>())
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{Matrix, Vec2};
+
     const SAMPLE: &str = r"467..114..
 ...*......
 ..35..633.
@@ -196,5 +430,127 @@ mod tests {
     #[test]
     fn test_part2() {
         assert_eq!(super::part2(SAMPLE), 467835);
+        assert_eq!(super::part2_neighbours(SAMPLE), 467835);
+    }
+
+    #[test]
+    fn test_graph_variants() {
+        assert_eq!(super::part1_graph(SAMPLE), 4361);
+        assert_eq!(super::part2_graph(SAMPLE), 467835);
+
+        let edges = "*.....12\n3......*\n.......4\n5*6....#";
+        assert_eq!(super::part1_graph(edges), super::part1(edges));
+        assert_eq!(super::part2_graph(edges), super::part2(edges));
+    }
+
+    #[test]
+    fn test_stream() {
+        use std::io::BufReader;
+
+        assert_eq!(super::part1_stream(SAMPLE), 4361);
+
+        let edges = "*.....12\n3......*\n.......4\n5*6....#";
+        for input in [SAMPLE, edges, "12*34", "..7\n*..\n2.."] {
+            let with_newline = format!("{input}\n");
+            for input in [input, &with_newline] {
+                // a tiny buffer forces lines to arrive in several reads
+                let reader = BufReader::with_capacity(3, input.as_bytes());
+                assert_eq!(super::sum_part_numbers(reader).unwrap(), super::part1(input));
+            }
+        }
+    }
+
+    #[test]
+    fn test_schematic_queries() {
+        use super::{Schematic, Symbol};
+
+        let schematic = Schematic::parse(SAMPLE);
+        let values = |numbers: Vec<&super::Number>| -> Vec<u32> {
+            numbers.into_iter().map(|number| number.value).collect()
+        };
+
+        assert_eq!(values(schematic.numbers_adjacent_to('#').collect()), [633]);
+        assert_eq!(values(schematic.numbers_adjacent_to('*').collect()), [467, 35, 617, 755, 598]);
+        assert_eq!(values(schematic.numbers_adjacent_to('%').collect()), [0u32; 0]);
+
+        let gears: Vec<_> =
+            schematic.symbols_with_degree(2).map(|s| &schematic.symbols[s]).collect();
+        assert_eq!(gears, [&Symbol { x: 3, y: 1, ch: '*' }, &Symbol { x: 5, y: 8, ch: '*' }]);
+        let lonely: Vec<_> =
+            schematic.symbols_with_degree(1).map(|s| schematic.symbols[s].ch).collect();
+        assert_eq!(lonely, ['#', '*', '+', '$']);
+
+        let n592 = schematic.numbers.iter().position(|number| number.value == 592).unwrap();
+        assert_eq!(schematic.numbers[n592].x, 2);
+        assert_eq!(schematic.numbers[n592].len, 3);
+        assert_eq!(schematic.symbols_of(n592).map(|symbol| symbol.ch).collect::<Vec<_>>(), ['+']);
+    }
+
+    #[test]
+    fn test_matrix_bounds() {
+        for input in ["12.\n.*.\n", "12.\n.*.", "12.\n.*.\n..."] {
+            let mat = Matrix::new(input);
+            assert_eq!(mat.width(), 3);
+            assert_eq!(mat.height(), input.lines().count());
+
+            assert_eq!(mat.to_index(Vec2(2, 1)), Some(6));
+            assert_eq!(mat.to_index(Vec2(3, 0)), None, "newline column is outside");
+            assert_eq!(mat.to_index(Vec2(-1, 0)), None);
+            assert_eq!(mat.to_index(Vec2(0, -1)), None);
+            assert_eq!(mat.to_index(Vec2(0, mat.height() as i16)), None);
+            // x and y are checked against their own axes
+            assert_eq!(mat.to_index(Vec2(1, 2)).is_some(), mat.height() > 2);
+        }
+
+        let single = Matrix::new("4*2");
+        assert_eq!((single.width(), single.height()), (3, 1));
+        assert_eq!(single.get(Vec2(2, 0)), Some(b'2'));
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let mat = Matrix::new("abc\ndef\nghi");
+        let around = |x, y| -> String {
+            mat.neighbours(Vec2(x, y)).map(|coord| mat.get(coord).unwrap() as char).collect()
+        };
+        assert_eq!(around(0, 0), "bde");
+        assert_eq!(around(2, 2), "efh");
+        assert_eq!(around(2, 1), "bcehi");
+        assert_eq!(around(1, 1), "abcdfghi");
+
+        let rows: Vec<_> = mat.window_rows(Vec2(2, 0)).collect();
+        assert_eq!(rows, [1..=2, 5..=6]);
+    }
+
+    #[test]
+    fn test_number_at() {
+        let mat = Matrix::new("..123\n45*..");
+        assert_eq!(mat.number_at(Vec2(4, 0)), Some((Vec2(2, 0), 123)));
+        assert_eq!(mat.number_at(Vec2(2, 0)), Some((Vec2(2, 0), 123)));
+        assert_eq!(mat.number_at(Vec2(0, 1)), Some((Vec2(0, 1), 45)));
+        assert_eq!(mat.number_at(Vec2(2, 1)), None);
+        assert_eq!(mat.number_at(Vec2(5, 0)), None);
+    }
+
+    #[test]
+    fn test_edges() {
+        // symbols in the corners, numbers at the start and end of lines
+        let input = "*.....12\n3......*\n.......4\n5*6....#";
+        assert_eq!(super::part1(input), 12 + 3 + 4 + 5 + 6);
+        assert_eq!(super::part1(&format!("{input}\n")), super::part1(input));
+        assert_eq!(super::part1("12*34"), 46);
+
+        for part2 in [super::part2, super::part2_neighbours] {
+            assert_eq!(part2(input), 12 * 4 + 5 * 6);
+            assert_eq!(part2(&format!("{input}\n")), part2(input));
+
+            // a gear in the first column must not wrap around to the previous line
+            assert_eq!(part2("..7\n*..\n2.."), 0);
+            assert_eq!(part2("7..\n*..\n2.."), 14);
+            // a gear in the last column must not wrap around to the next line
+            assert_eq!(part2("..*\n7..\n..2"), 0);
+
+            assert_eq!(part2("12*34"), 408);
+        }
     }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::{error, fmt};
 
 struct Line<L, R> {
     left:  L,
@@ -32,13 +33,41 @@ where
     }
 }
 
-#[aoc_runner_derive::aoc(day4, part1)]
-pub fn part1(input: &str) -> u32 {
+fn matches_hash(line: &str) -> usize {
+    let line = Line::<HashSet<u32>, Vec<u32>>::parse(line);
+    line.right.iter().filter(|&v| line.left.contains(v)).count()
+}
+
+/// The set of numbers as bits, or `None` if a number does not fit or is repeated.
+struct Bitmask(Option<u128>);
+
+impl FromIterator<u32> for Bitmask {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut mask = 0u128;
+        for number in iter {
+            match 1u128.checked_shl(number) {
+                Some(bit) if mask & bit == 0 => mask |= bit,
+                _ => return Self(None),
+            }
+        }
+        Self(Some(mask))
+    }
+}
+
+fn matches_bitmask(line: &str) -> usize {
+    match Line::<Bitmask, Bitmask>::parse(line) {
+        Line { left: Bitmask(Some(left)), right: Bitmask(Some(right)) } => {
+            (left & right).count_ones() as usize
+        }
+        _ => matches_hash(line),
+    }
+}
+
+fn solve_1(input: &str, matches: impl Fn(&str) -> usize) -> u32 {
     input
         .lines()
         .map(|line| {
-            let line = Line::<HashSet<u32>, Vec<u32>>::parse(line);
-            let matches = line.right.iter().filter(|&v| line.left.contains(v)).count();
+            let matches = matches(line);
             if matches > 0 {
                 1 << (matches - 1)
             } else {
@@ -48,21 +77,14 @@ pub fn part1(input: &str) -> u32 {
         .sum()
 }
 
-#[aoc_runner_derive::aoc(day4, part2)]
-pub fn part2(input: &str) -> u32 {
+fn solve_2(input: &str, matches: impl Fn(&str) -> usize) -> u32 {
     struct CardStack {
         matches: usize,
         copies:  u32,
     }
 
-    let mut stacks: Vec<_> = input
-        .lines()
-        .map(|line| {
-            let line = Line::<HashSet<u32>, Vec<u32>>::parse(line);
-            let matches = line.right.iter().filter(|&v| line.left.contains(v)).count();
-            CardStack { matches, copies: 1 }
-        })
-        .collect();
+    let mut stacks: Vec<_> =
+        input.lines().map(|line| CardStack { matches: matches(line), copies: 1 }).collect();
 
     let mut sum = 0;
     for i in 0..stacks.len() {
@@ -75,6 +97,64 @@ pub fn part2(input: &str) -> u32 {
     sum
 }
 
+#[aoc_runner_derive::aoc(day4, part1)]
+pub fn part1(input: &str) -> u32 { solve_1(input, matches_hash) }
+
+#[aoc_runner_derive::aoc(day4, part1, Bitmask)]
+pub fn part1_bitmask(input: &str) -> u32 { solve_1(input, matches_bitmask) }
+
+#[aoc_runner_derive::aoc(day4, part2)]
+pub fn part2(input: &str) -> u32 { solve_2(input, matches_hash) }
+
+#[aoc_runner_derive::aoc(day4, part2, Bitmask)]
+pub fn part2_bitmask(input: &str) -> u32 { solve_2(input, matches_bitmask) }
+
+/// The number of card copies no longer fits in a `u64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+    /// 0-based index of the card at which the count overflowed
+    pub card: usize,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "number of scratchcards overflows u64 at card {}", self.card + 1)
+    }
+}
+
+impl error::Error for Overflow {}
+
+/// Counts the scratchcards after all copies are won, given the matches of each card.
+///
+/// Copies that would go past the last card are dropped.
+/// Each card adds its copies to a range of later cards,
+/// so a difference array keeps this linear in the number of cards.
+pub fn count_cards(matches: &[usize]) -> Result<u64, Overflow> {
+    // ending[i] is the number of extra copies that stop applying at card i
+    let mut ending = vec![0u64; matches.len() + 1];
+    let mut active = 0u64;
+    let mut sum = 0u64;
+
+    for (card, &card_matches) in matches.iter().enumerate() {
+        active -= ending[card];
+        let copies = active.checked_add(1).ok_or(Overflow { card })?;
+        sum = sum.checked_add(copies).ok_or(Overflow { card })?;
+
+        let end = (card + card_matches).min(matches.len() - 1);
+        if end > card {
+            active = active.checked_add(copies).ok_or(Overflow { card })?;
+            ending[end + 1] += copies;
+        }
+    }
+    Ok(sum)
+}
+
+#[aoc_runner_derive::aoc(day4, part2, Cascade)]
+pub fn part2_cascade(input: &str) -> Result<u64, Overflow> {
+    let matches: Vec<_> = input.lines().map(matches_bitmask).collect();
+    count_cards(&matches)
+}
+
 #[cfg(test)]
 mod tests {
     const SAMPLE: &str = r"Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
@@ -93,4 +173,51 @@ Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
     fn test_part2() {
         assert_eq!(super::part2(SAMPLE), 30);
     }
+
+    #[test]
+    fn test_bitmask() {
+        assert_eq!(super::part1_bitmask(SAMPLE), 13);
+        assert_eq!(super::part2_bitmask(SAMPLE), 30);
+
+        for line in SAMPLE.lines() {
+            assert_eq!(super::matches_bitmask(line), super::matches_hash(line));
+        }
+    }
+
+    #[test]
+    fn test_cascade() {
+        use super::{count_cards, Overflow};
+
+        assert_eq!(super::part2_cascade(SAMPLE), Ok(30));
+
+        // matches past the last card are clamped instead of panicking
+        assert_eq!(super::part2_cascade("Card 1: 1 2 | 1 2"), Ok(1));
+        assert_eq!(count_cards(&[3, 5, 0]), Ok(1 + 2 + 4));
+        assert_eq!(count_cards(&[]), Ok(0));
+
+        // every card copies all later ones, doubling the copies each time
+        assert_eq!(count_cards(&[63; 63]), Ok(u64::MAX >> 1));
+        assert_eq!(count_cards(&[64; 64]), Ok(u64::MAX));
+        assert_eq!(count_cards(&[65; 65]), Err(Overflow { card: 64 }));
+        assert_eq!(
+            Overflow { card: 64 }.to_string(),
+            "number of scratchcards overflows u64 at card 65"
+        );
+    }
+
+    #[test]
+    fn test_bitmask_fallback() {
+        use super::{matches_bitmask, Bitmask};
+
+        assert!(Bitmask::from_iter([0, 127]).0.is_some());
+        assert!(Bitmask::from_iter([5, 128]).0.is_none());
+        assert!(Bitmask::from_iter([5, 5]).0.is_none());
+
+        // out of range for the bitmask
+        assert_eq!(matches_bitmask("Card 1: 200 3 | 200 4 3"), 2);
+        // 1 << 200 would wrap to 1 << 72 if it were not checked
+        assert_eq!(matches_bitmask("Card 1: 72 | 200"), 0);
+        // a repeated number on the right counts twice
+        assert_eq!(matches_bitmask("Card 1: 7 8 | 7 7 9"), 2);
+    }
 }
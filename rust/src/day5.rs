@@ -1,82 +1,6 @@
-use std::iter;
+use crate::interval::{Interval, IntervalMap, IntervalSet, MaxValueError, Piece};
 
-#[derive(Default)]
-struct Map {
-    sort_src: Vec<Triple>,
-}
-
-impl Map {
-    fn find_src_sorted(&self, src: u64) -> u64 {
-        let Some(triple) = self.sort_src[..self.sort_src.partition_point(|t| t.src <= src)].last()
-        else {
-            return src;
-        };
-        let delta = src - triple.src;
-        if delta < triple.width {
-            triple.dest + delta
-        } else {
-            src
-        }
-    }
-
-    fn find_src_range_sorted(&self, src: Range) -> impl Iterator<Item = Range> + '_ {
-        let left_pp = self.sort_src.partition_point(|triple| triple.src + triple.width < src.start);
-        let mut relevant = &self.sort_src[left_pp..];
-        relevant =
-            &relevant[..relevant.partition_point(|triple| triple.src < src.start + src.width)];
-
-        let mut last_end = src.start;
-        let final_end = src.start + src.width;
-
-        let last_relevant = relevant.last().copied();
-
-        relevant
-            .iter()
-            .flat_map(move |triple| {
-                // region without mapping
-                let gap = Range { start: last_end, width: triple.src.saturating_sub(last_end) };
-                last_end += gap.width;
-                // region with mapping
-                let mapped = Range {
-                    start: triple.dest + (last_end - triple.src),
-                    width: (triple.src + triple.width).min(final_end) - last_end,
-                };
-                last_end += mapped.width;
-                [gap, mapped]
-            })
-            .chain(iter::once_with(move || {
-                match last_relevant {
-                    Some(last) => {
-                        // gap from last relevant
-                        Range {
-                            start: last.src + last.width,
-                            width: final_end.saturating_sub(last.src + last.width),
-                        }
-                    }
-                    None => {
-                        // relevant is empty, i.e. no overlapping triples
-                        src
-                    }
-                }
-            }))
-            .filter(|range| range.width > 0)
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Range {
-    start: u64,
-    width: u64,
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Triple {
-    dest:  u64,
-    src:   u64,
-    width: u64,
-}
-
-fn parse_input(input: &str) -> (impl Iterator<Item = u64> + '_, Vec<Map>) {
+fn parse_input(input: &str) -> (impl Iterator<Item = u64> + '_, Vec<IntervalMap<u64>>) {
     let (seeds, input) = input.split_once('\n').unwrap();
     let seeds =
         seeds.strip_prefix("seeds: ").unwrap().split(' ').map(|s| s.parse::<u64>().unwrap());
@@ -86,7 +10,7 @@ fn parse_input(input: &str) -> (impl Iterator<Item = u64> + '_, Vec<Map>) {
 
     for line in input.lines().filter(|line| !line.is_empty()) {
         if let Some(_title) = line.strip_suffix(" map:") {
-            maps.push(Map::default());
+            maps.push(Vec::new());
             active_map = maps.last_mut();
             continue;
         }
@@ -95,62 +19,94 @@ fn parse_input(input: &str) -> (impl Iterator<Item = u64> + '_, Vec<Map>) {
             Ok(x) => x,
             Err(err) => panic!("parse {s:?}: {err}"),
         });
-        let triple = Triple {
-            dest:  iter.next().unwrap(),
-            src:   iter.next().unwrap(),
-            width: iter.next().unwrap(),
-        };
+        let dest = iter.next().unwrap();
+        let src = iter.next().unwrap();
+        let width = iter.next().unwrap();
 
+        let piece = Interval::checked_with_width(src, width).and_then(|src| Piece::new(src, dest));
         let map = active_map.as_mut().unwrap();
-        map.sort_src.push(triple);
+        map.push(piece.unwrap_or_else(|err| panic!("map line {line:?}: {err}")));
     }
 
-    for map in &mut maps {
-        map.sort_src.sort_by_key(|t| t.src);
-    }
+    (seeds, maps.into_iter().map(IntervalMap::from_iter).collect())
+}
 
-    (seeds, maps)
+fn seed_ranges(seeds: impl Iterator<Item = u64>) -> IntervalSet<u64> {
+    seeds
+        .array_chunks()
+        .map(|[start, width]| {
+            Interval::checked_with_width(start, width)
+                .unwrap_or_else(|err| panic!("seed range {start} {width}: {err}"))
+        })
+        .collect()
+}
+
+/// Composes the maps of all stages into a single map from seed to location.
+fn compose(maps: &[IntervalMap<u64>]) -> IntervalMap<u64> {
+    maps.iter().fold(IntervalMap::default(), |composed, map| composed.then(map))
 }
 
 #[aoc_runner_derive::aoc(day5, part1)]
 pub fn part1(input: &str) -> u64 {
     let (seeds, maps) = parse_input(input);
+    let almanac = compose(&maps);
 
-    seeds.map(|seed| maps.iter().fold(seed, |src, map| map.find_src_sorted(src))).min().unwrap()
+    seeds.map(|seed| almanac.get(seed)).min().unwrap()
 }
 
-#[aoc_runner_derive::aoc(day5, part2)]
+#[aoc_runner_derive::aoc(day5, part2, Forward)]
 pub fn part2(input: &str) -> u64 {
     let (seeds, maps) = parse_input(input);
+    let almanac = compose(&maps);
+
+    let seeds = seed_ranges(seeds);
+
+    almanac.map_set(&seeds).min().unwrap()
+}
+
+/// Scans location intervals upwards and returns the first one that leads back to a seed range.
+#[aoc_runner_derive::aoc(day5, part2, Reverse)]
+pub fn part2_reverse(input: &str) -> u64 {
+    let (seeds, maps) = parse_input(input);
+    let almanac = compose(&maps);
+
+    let seeds = seed_ranges(seeds);
 
-    fn fold_tail_range<B>(
-        range: Range,
-        maps: &[Map],
-        state: B,
-        fold_fn: &mut impl FnMut(B, Range) -> B,
-    ) -> B {
-        if maps.is_empty() {
-            fold_fn(state, range)
-        } else {
-            let (first, rest) = maps.split_first().unwrap();
-            first.find_src_range_sorted(range).fold(state, |state, dest_range| {
-                fold_tail_range(dest_range, rest, state, &mut *fold_fn)
-            })
+    let mut best = u64::MAX;
+    for inverse in almanac.inverse_pieces() {
+        if inverse.src.start >= best {
+            break;
+        }
+        if let Some(seed) = seeds.intersection(&inverse.image().into()).min() {
+            best = best.min(inverse.invert().apply(seed));
         }
     }
+    best
+}
 
-    seeds
-        .array_chunks()
-        .map(|[start, width]| Range { start, width })
-        .map(|range| {
-            fold_tail_range(range, &maps[..], u64::MAX, &mut |min, range| min.min(range.start))
-        })
-        .min()
-        .unwrap()
+/// Returns every number that leads to `location` in the almanac `input`,
+/// whether or not it is in a seed range.
+///
+/// `u64::MAX` cannot be represented as an interval and is rejected.
+pub fn numbers_for_location(input: &str, location: u64) -> Result<IntervalSet<u64>, MaxValueError> {
+    let (_, maps) = parse_input(input);
+    Ok(compose(&maps).preimage(Interval::checked_with_width(location, 1)?))
+}
+
+/// Returns the parts of the seed ranges of `input` that lead to `location`.
+///
+/// `u64::MAX` cannot be represented as an interval and is rejected.
+pub fn seeds_for_location(input: &str, location: u64) -> Result<IntervalSet<u64>, MaxValueError> {
+    let (seeds, maps) = parse_input(input);
+    let numbers = compose(&maps).preimage(Interval::checked_with_width(location, 1)?);
+    Ok(seed_ranges(seeds).intersection(&numbers))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::interval::{Interval, IntervalMap, IntervalSet, MaxValueError, Piece};
+    use crate::test_util::Xorshift;
+
     const SAMPLE: &str = r"seeds: 79 14 55 13
 
 seed-to-soil map:
@@ -194,4 +150,67 @@ humidity-to-location map:
     fn test_part2() {
         assert_eq!(super::part2(SAMPLE), 46);
     }
+
+    #[test]
+    fn test_part2_reverse() {
+        assert_eq!(super::part2_reverse(SAMPLE), 46);
+    }
+
+    #[test]
+    fn test_numbers_for_location() {
+        let numbers = super::numbers_for_location(SAMPLE, 46).unwrap();
+        assert!(numbers.contains(82));
+
+        let (_, maps) = SAMPLE.split_once('\n').unwrap();
+        for seed in numbers.iter().flat_map(|interval| interval.start..interval.end) {
+            assert_eq!(super::part1(&format!("seeds: {seed}\n{maps}")), 46);
+        }
+
+        assert_eq!(super::numbers_for_location(SAMPLE, u64::MAX), Err(MaxValueError));
+        assert!(super::numbers_for_location(SAMPLE, u64::MAX - 1).unwrap().contains(u64::MAX - 1));
+    }
+
+    #[test]
+    fn test_seeds_for_location() {
+        let seeds = super::seeds_for_location(SAMPLE, 46).unwrap();
+        assert_eq!(seeds, IntervalSet::from(Interval::checked_with_width(82, 1).unwrap()));
+
+        // the numbers leading to location 0 are all outside the seed ranges
+        assert!(!super::numbers_for_location(SAMPLE, 0).unwrap().is_empty());
+        assert!(super::seeds_for_location(SAMPLE, 0).unwrap().is_empty());
+
+        assert_eq!(super::seeds_for_location(SAMPLE, u64::MAX), Err(MaxValueError));
+        assert!(super::seeds_for_location(SAMPLE, u64::MAX - 1).unwrap().is_empty());
+    }
+
+    fn random_map(rng: &mut Xorshift) -> IntervalMap<u64> {
+        let mut src = 0;
+        let mut pieces = Vec::new();
+        for _ in 0..rng.below(6) {
+            src += rng.below(20);
+            let width = rng.below(30) + 1;
+            pieces.push(Piece { src: Interval::with_width(src, width), dest: rng.below(200) });
+            src += width;
+        }
+        pieces.into_iter().collect()
+    }
+
+    #[test]
+    fn test_compose_matches_sequential() {
+        let mut rng = Xorshift::new(5);
+        for _ in 0..200 {
+            let maps: Vec<_> = (0..rng.below(8)).map(|_| random_map(&mut rng)).collect();
+            let composed = super::compose(&maps);
+
+            for value in 0..300 {
+                let sequential = maps.iter().fold(value, |value, map| map.get(value));
+                assert_eq!(composed.get(value), sequential, "value {value} through {maps:?}");
+            }
+
+            let ranges: IntervalSet<u64> =
+                (0..4).map(|_| Interval::with_width(rng.below(250), rng.below(50))).collect();
+            let sequential = maps.iter().fold(ranges.clone(), |ranges, map| map.map_set(&ranges));
+            assert_eq!(composed.map_set(&ranges), sequential, "{ranges:?} through {maps:?}");
+        }
+    }
 }
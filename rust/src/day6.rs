@@ -26,9 +26,9 @@ pub fn part2(input: &str) -> u64 {
     let mut lines = input.lines();
 
     fn parse(line: &str) -> u64 {
-        line.splitn(2, ':')
-            .nth(1)
+        line.split_once(':')
             .unwrap()
+            .1
             .chars()
             .filter_map(|ch: char| ch.to_digit(10))
             .fold(0, |sum, digit| sum * 10 + digit as u64)
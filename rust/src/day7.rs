@@ -1,14 +1,17 @@
+use std::sync::OnceLock;
+use std::{fmt, mem};
+
 use arrayvec::ArrayVec;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct Index(u8);
+pub struct Index(u8);
 
 fn indexer_1(b: char) -> Index { Index(12 - "AKQJT98765432".find(b).unwrap() as u8) }
 
 fn indexer_2(b: char) -> Index { Index(12 - "AKQT98765432J".find(b).unwrap() as u8) }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum Type {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Type {
     High,
     OnePair,
     TwoPairs,
@@ -105,6 +108,46 @@ fn get_type_2(hand: [Index; 5]) -> Type {
     }
 }
 
+/// Reference for `get_type_2` that tries every substitution of every joker.
+fn get_type_2_brute_force(hand: [Index; 5]) -> Type {
+    let jokers: ArrayVec<usize, 5> = (0..5).filter(|&i| hand[i] == Index(0)).collect();
+
+    let mut best = Type::High;
+    for mut code in 0..12u32.pow(jokers.len() as u32) {
+        let mut substituted = hand;
+        for &i in &jokers {
+            substituted[i] = Index(1 + (code % 12) as u8);
+            code /= 12;
+        }
+        best = best.max(get_type_1(substituted));
+    }
+    best
+}
+
+const HAND_CODES: usize = 13usize.pow(5);
+
+/// Encodes a hand in base 13, with the first card as the least significant digit.
+fn hand_code(hand: [Index; 5]) -> usize {
+    hand.iter().rev().fold(0, |code, card| code * 13 + card.0 as usize)
+}
+
+fn hand_from_code(mut code: usize) -> [Index; 5] {
+    [(); 5].map(|()| {
+        let card = Index((code % 13) as u8);
+        code /= 13;
+        card
+    })
+}
+
+/// Looks up the type from a table precomputed by `get_type_2_brute_force`.
+fn get_type_2_lookup(hand: [Index; 5]) -> Type {
+    static TABLE: OnceLock<Vec<Type>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        (0..HAND_CODES).map(|code| get_type_2_brute_force(hand_from_code(code))).collect()
+    });
+    table[hand_code(hand)]
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct Line {
     ty:   Type,
@@ -128,29 +171,168 @@ fn solve_with_prefix_sum(lines: &[Line]) -> u32 {
         .sum()
 }
 
+fn parse_lines<'t>(
+    input: &'t str,
+    indexer: impl Fn(char) -> Index + Copy + 't,
+    get_type: impl Fn([Index; 5]) -> Type + 't,
+) -> impl Iterator<Item = Line> + 't {
+    input.lines().map(move |line| {
+        let mut line = line.chars();
+        let hand: ArrayVec<Index, 5> = line.by_ref().take(5).map(indexer).collect();
+        let hand = hand.into_inner().unwrap();
+        let ty = get_type(hand);
+        let bid = line
+            .filter_map(|ch: char| ch.to_digit(10))
+            .fold(0, |sum, digit| sum * 10 + digit as u16);
+        Line { ty, hand, bid }
+    })
+}
+
 fn solve(
     input: &str,
     indexer: impl Fn(char) -> Index + Copy,
     get_type: impl Fn([Index; 5]) -> Type,
     solver: impl Fn(&[Line]) -> u32,
 ) -> u32 {
-    let mut lines: Vec<Line> = input
+    let mut lines: Vec<Line> = parse_lines(input, indexer, get_type).collect();
+
+    lines.sort_unstable();
+
+    solver(&lines)
+}
+
+/// Packs the type into bits 36..39, each card into 4 bits from bit 16 and the bid into bits 0..16,
+/// such that comparing keys is equivalent to comparing `Line`s.
+fn pack_key(line: &Line) -> u64 {
+    let hand = line.hand.iter().fold(0, |key, card| key << 4 | card.0 as u64);
+    ((line.ty as u64) << 20 | hand) << 16 | line.bid as u64
+}
+
+const KEY_BITS: u32 = 39;
+
+/// Stable LSD radix sort of the keys, one byte per pass.
+fn radix_sort(keys: &mut Vec<u64>, buffer: &mut Vec<u64>) {
+    buffer.resize(keys.len(), 0);
+
+    for shift in (0..KEY_BITS).step_by(8) {
+        let digit = |key: u64| ((key >> shift) & 0xff) as usize;
+
+        let mut offsets = [0usize; 257];
+        for &key in keys.iter() {
+            offsets[digit(key) + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+
+        for &key in keys.iter() {
+            let offset = &mut offsets[digit(key)];
+            buffer[*offset] = key;
+            *offset += 1;
+        }
+        mem::swap(keys, buffer);
+    }
+}
+
+fn solve_radix(
+    input: &str,
+    indexer: impl Fn(char) -> Index + Copy,
+    get_type: impl Fn([Index; 5]) -> Type,
+) -> u32 {
+    let mut keys: Vec<u64> =
+        parse_lines(input, indexer, get_type).map(|line| pack_key(&line)).collect();
+
+    radix_sort(&mut keys, &mut Vec::new());
+
+    (1..).zip(&keys).map(|(rank, &key)| rank * (key & 0xffff) as u32).sum()
+}
+
+/// A poker-like rule set.
+pub struct CardRules {
+    /// all card labels from the strongest to the weakest
+    pub order:     &'static str,
+    /// a card that acts as whichever card makes the strongest hand type
+    pub wildcard:  Option<char>,
+    pub hand_size: usize,
+}
+
+pub const RULES_1: CardRules =
+    CardRules { order: "AKQJT98765432", wildcard: None, hand_size: 5 };
+pub const RULES_2: CardRules =
+    CardRules { order: "AKQT98765432J", wildcard: Some('J'), hand_size: 5 };
+
+/// Group sizes of a hand in descending order.
+///
+/// Comparing shapes lexicographically ranks hand types, e.g. `[3, 2]` (full house)
+/// is stronger than `[3, 1, 1]` (three of a kind).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Shape(pub Vec<u8>);
+
+impl Shape {
+    fn five_card_type(&self) -> Type {
+        match self.0[..] {
+            [5] => Type::FiveOfKind,
+            [4, ..] => Type::FourOfKind,
+            [3, 2] => Type::FullHouse,
+            [3, ..] => Type::ThreeOfKind,
+            [2, 2, ..] => Type::TwoPairs,
+            [2, ..] => Type::OnePair,
+            _ => Type::High,
+        }
+    }
+}
+
+impl CardRules {
+    pub fn indexer(&self, card: char) -> Index {
+        let position = self.order.chars().position(|ch| ch == card).expect("unknown card");
+        Index((self.order.chars().count() - 1 - position) as u8)
+    }
+
+    /// Returns the shape of the strongest hand obtained by replacing the wildcards.
+    ///
+    /// Adding every wildcard to the largest group is always optimal,
+    /// since it makes the first element of the shape as large as possible.
+    pub fn shape(&self, hand: &[Index]) -> Shape {
+        let mut stats = vec![0u8; self.order.chars().count()];
+        for card in hand {
+            stats[card.0 as usize] += 1;
+        }
+
+        let wildcards = match self.wildcard {
+            Some(wildcard) => mem::take(&mut stats[self.indexer(wildcard).0 as usize]),
+            None => 0,
+        };
+
+        let mut groups: Vec<u8> = stats.into_iter().filter(|&stat| stat > 0).collect();
+        groups.sort_unstable_by(|a, b| b.cmp(a));
+        match groups.first_mut() {
+            Some(largest) => *largest += wildcards,
+            None => groups.push(wildcards),
+        }
+        Shape(groups)
+    }
+
+    pub fn get_type(&self, hand: &[Index]) -> Type {
+        assert_eq!(self.hand_size, 5, "hand types are only defined for five cards");
+        self.shape(hand).five_card_type()
+    }
+}
+
+/// Ranks hands of any size by their shape under `rules`.
+fn solve_with_rules(input: &str, rules: &CardRules) -> u64 {
+    let mut lines: Vec<(Shape, Vec<Index>, u64)> = input
         .lines()
         .map(|line| {
-            let mut line = line.chars();
-            let hand: ArrayVec<Index, 5> = line.by_ref().take(5).map(indexer).collect();
-            let hand = hand.into_inner().unwrap();
-            let ty = get_type(hand);
-            let bid = line
-                .filter_map(|ch: char| ch.to_digit(10))
-                .fold(0, |sum, digit| sum * 10 + digit as u16);
-            Line { ty, hand, bid }
+            let (hand, bid) = line.split_once(' ').expect("has space");
+            let hand: Vec<Index> = hand.chars().map(|card| rules.indexer(card)).collect();
+            assert_eq!(hand.len(), rules.hand_size, "wrong hand size");
+            (rules.shape(&hand), hand, bid.parse().unwrap())
         })
         .collect();
 
     lines.sort_unstable();
 
-    solver(&lines)
+    (1..).zip(&lines).map(|(rank, (_, _, bid))| rank * bid).sum()
 }
 
 #[aoc_runner_derive::aoc(day7, part1, MulAdd)]
@@ -169,8 +351,114 @@ pub fn part2_prefix_sum(input: &str) -> u32 {
     solve(input, indexer_2, get_type_2, solve_with_prefix_sum)
 }
 
+#[aoc_runner_derive::aoc(day7, part1, Radix)]
+pub fn part1_radix(input: &str) -> u32 { solve_radix(input, indexer_1, get_type_1) }
+
+#[aoc_runner_derive::aoc(day7, part2, Radix)]
+pub fn part2_radix(input: &str) -> u32 { solve_radix(input, indexer_2, get_type_2) }
+
+#[aoc_runner_derive::aoc(day7, part2, Lookup)]
+pub fn part2_lookup(input: &str) -> u32 {
+    solve(input, indexer_2, get_type_2_lookup, solve_with_mul_add)
+}
+
+#[aoc_runner_derive::aoc(day7, part1, Rules)]
+pub fn part1_rules(input: &str) -> u64 { solve_with_rules(input, &RULES_1) }
+
+#[aoc_runner_derive::aoc(day7, part2, Rules)]
+pub fn part2_rules(input: &str) -> u64 { solve_with_rules(input, &RULES_2) }
+
+/// A hand with its position in the ranking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedHand<'t> {
+    pub hand:     &'t str,
+    pub ty:       Type,
+    pub rank:     u32,
+    pub bid:      u16,
+    /// `rank * bid`
+    pub winnings: u32,
+}
+
+/// All hands in ascending order of rank.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report<'t>(pub Vec<RankedHand<'t>>);
+
+impl Report<'_> {
+    pub fn total(&self) -> u32 { self.0.iter().map(|hand| hand.winnings).sum() }
+
+    pub fn to_csv(&self) -> String {
+        let mut output = String::from("rank,hand,type,bid,winnings\n");
+        for RankedHand { hand, ty, rank, bid, winnings } in &self.0 {
+            output.push_str(&format!("{rank},{hand},{ty:?},{bid},{winnings}\n"));
+        }
+        output
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>5}  {:<5}  {:<11}  {:>5}  {:>9}",
+            "rank", "hand", "type", "bid", "winnings"
+        )?;
+        for RankedHand { hand, ty, rank, bid, winnings } in &self.0 {
+            let ty = format!("{ty:?}");
+            writeln!(f, "{rank:>5}  {hand:<5}  {ty:<11}  {bid:>5}  {winnings:>9}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Hand types, and therefore reports, are only defined for five-card hands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedHandSize(pub usize);
+
+impl fmt::Display for UnsupportedHandSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hand types are only defined for five cards, not {}", self.0)
+    }
+}
+
+/// Ranks the five-card hands in `input` under `rules`.
+pub fn report<'t>(input: &'t str, rules: &CardRules) -> Result<Report<'t>, UnsupportedHandSize> {
+    if rules.hand_size != 5 {
+        return Err(UnsupportedHandSize(rules.hand_size));
+    }
+
+    let mut lines: Vec<(Line, &str)> = input
+        .lines()
+        .map(|line| {
+            let (hand_str, bid) = line.split_once(' ').expect("has space");
+            let hand: ArrayVec<Index, 5> =
+                hand_str.chars().map(|card| rules.indexer(card)).collect();
+            let hand = hand.into_inner().expect("wrong hand size");
+            let line = Line { ty: rules.get_type(&hand), hand, bid: bid.parse().unwrap() };
+            (line, hand_str)
+        })
+        .collect();
+
+    lines.sort_unstable();
+
+    Ok(Report(
+        (1..)
+            .zip(lines)
+            .map(|(rank, (line, hand))| RankedHand {
+                hand,
+                ty: line.ty,
+                rank,
+                bid: line.bid,
+                winnings: rank * line.bid as u32,
+            })
+            .collect(),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{CardRules, Index, Shape, RULES_1, RULES_2};
+    use crate::test_util::Xorshift;
+
     const SAMPLE: &str = r"32T3K 765
 T55J5 684
 KK677 28
@@ -186,4 +474,139 @@ QQQJA 483";
     fn test_part2_mul_add() {
         assert_eq!(super::part2_mul_add(SAMPLE), 5905);
     }
+
+    #[test]
+    fn test_part1_radix() {
+        assert_eq!(super::part1_radix(SAMPLE), 6440);
+    }
+
+    #[test]
+    fn test_part2_radix() {
+        assert_eq!(super::part2_radix(SAMPLE), 5905);
+    }
+
+    #[test]
+    fn test_radix_matches_sort() {
+        // distinct hands, as in the puzzle input
+        let mut rng = Xorshift::new(38);
+        let mut codes: Vec<usize> = (0..super::HAND_CODES).step_by(373).collect();
+        for i in (1..codes.len()).rev() {
+            codes.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+
+        let labels = b"23456789TJQKA";
+        let mut input = String::new();
+        for code in codes {
+            for card in super::hand_from_code(code) {
+                input.push(labels[card.0 as usize] as char);
+            }
+            input.push_str(&format!(" {}\n", rng.below(1000) + 1));
+        }
+
+        assert_eq!(super::part1_radix(&input), super::part1_mul_add(&input));
+        assert_eq!(super::part2_radix(&input), super::part2_mul_add(&input));
+    }
+
+    #[test]
+    fn test_radix_duplicate_hands() {
+        // equal hands are ranked by bid, like the derived order of `Line`
+        let input = "KK677 28\nKK677 5\n32T3K 765\nKK677 17\n";
+        assert_eq!(super::part1_radix(input), super::part1_mul_add(input));
+        assert_eq!(super::part1_radix(input), 765 + 2 * 5 + 3 * 17 + 4 * 28);
+        assert_eq!(super::part2_radix(input), super::part2_mul_add(input));
+    }
+
+    #[test]
+    fn test_part2_lookup() {
+        assert_eq!(super::part2_lookup(SAMPLE), 5905);
+    }
+
+    #[test]
+    fn test_hand_code() {
+        for_all_hands(|hand| {
+            assert_eq!(super::hand_from_code(super::hand_code(hand)), hand);
+        });
+    }
+
+    #[test]
+    fn test_get_type_2_matches_brute_force() {
+        for_all_hands(|hand| {
+            let expect = super::get_type_2(hand);
+            assert_eq!(super::get_type_2_brute_force(hand), expect, "{hand:?}");
+            assert_eq!(super::get_type_2_lookup(hand), expect, "lookup {hand:?}");
+        });
+    }
+
+    #[test]
+    fn test_report() {
+        let report = super::report(SAMPLE, &RULES_2).unwrap();
+        assert_eq!(report.total(), 5905);
+        assert_eq!(
+            report.to_csv(),
+            "rank,hand,type,bid,winnings
+1,32T3K,OnePair,765,765
+2,KK677,TwoPairs,28,56
+3,T55J5,FourOfKind,684,2052
+4,QQQJA,FourOfKind,483,1932
+5,KTJJT,FourOfKind,220,1100
+"
+        );
+        assert_eq!(
+            report.to_string(),
+            " rank  hand   type           bid   winnings
+    1  32T3K  OnePair        765        765
+    2  KK677  TwoPairs        28         56
+    3  T55J5  FourOfKind     684       2052
+    4  QQQJA  FourOfKind     483       1932
+    5  KTJJT  FourOfKind     220       1100
+"
+        );
+        assert_eq!(super::report(SAMPLE, &RULES_1).unwrap().total(), 6440);
+
+        // labels need not be ASCII
+        let rules = CardRules { order: "AKQ★T98765432", ..RULES_1 };
+        let report = super::report("★★★QQ 7\nAA223 3", &rules).unwrap();
+        assert_eq!(report.0[1].hand, "★★★QQ");
+        assert_eq!(report.0[1].ty, super::Type::FullHouse);
+
+        let rules = CardRules { hand_size: 4, ..RULES_1 };
+        let err = super::report("AKQJ 1", &rules).map(|_| ()).unwrap_err();
+        assert_eq!(err, super::UnsupportedHandSize(4));
+        assert_eq!(err.to_string(), "hand types are only defined for five cards, not 4");
+    }
+
+    #[test]
+    fn test_part1_rules() {
+        assert_eq!(super::part1_rules(SAMPLE), 6440);
+    }
+
+    #[test]
+    fn test_part2_rules() {
+        assert_eq!(super::part2_rules(SAMPLE), 5905);
+    }
+
+    /// Calls `f` with every five-card hand of 13 labels.
+    fn for_all_hands(f: impl FnMut([Index; 5])) {
+        (0..super::HAND_CODES).map(super::hand_from_code).for_each(f);
+    }
+
+    #[test]
+    fn test_rules_match_get_type() {
+        for_all_hands(|hand| {
+            assert_eq!(RULES_1.get_type(&hand), super::get_type_1(hand), "{hand:?}");
+            assert_eq!(RULES_2.get_type(&hand), super::get_type_2(hand), "{hand:?}");
+        });
+    }
+
+    #[test]
+    fn test_rules_other_sizes() {
+        let rules = CardRules { order: "ABC*", wildcard: Some('*'), hand_size: 7 };
+        let shape = |hand: &str| {
+            let hand: Vec<Index> = hand.chars().map(|card| rules.indexer(card)).collect();
+            rules.shape(&hand)
+        };
+        assert_eq!(shape("AABBC**"), Shape(vec![4, 2, 1]));
+        assert_eq!(shape("*******"), Shape(vec![7]));
+        assert!(shape("AAB**CC") > shape("AABBBCC"));
+    }
 }
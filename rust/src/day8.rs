@@ -1,45 +1,87 @@
+use std::collections::HashMap;
+use std::{error, fmt};
+
 use bitvec::vec::BitVec;
 
 trait NodeId: Clone + Copy + Default + Eq + Ord {
-    fn new(arr: [u8; 3]) -> Self;
-    fn u16(self) -> u16;
-    fn from_u16(i: u16) -> Self;
     fn usize(self) -> usize;
     fn from_usize(i: usize) -> Self;
 }
 
+/// A node ID computed directly from a label of three uppercase letters.
+trait FixedNodeId: NodeId {
+    /// Upper bound of `self.usize()` for all labels.
+    const SPACE: usize;
+
+    fn new(arr: [u8; 3]) -> Self;
+}
+
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 struct Packed(u16);
 
 impl NodeId for Packed {
+    fn usize(self) -> usize { self.0 as usize }
+    fn from_usize(i: usize) -> Self { Self(i as u16) }
+}
+
+impl FixedNodeId for Packed {
+    const SPACE: usize = 26 * 26 * 26;
+
     fn new(arr: [u8; 3]) -> Self {
         let d0 = (arr[0] - b'A') as u16;
         let d1 = (arr[1] - b'A') as u16;
         let d2 = (arr[2] - b'A') as u16;
         Self(d2 * 26 * 26 + d1 * 26 + d0)
     }
-
-    fn u16(self) -> u16 { self.0 }
-    fn from_u16(i: u16) -> Self { Self(i) }
-    fn usize(self) -> usize { self.0 as usize }
-    fn from_usize(i: usize) -> Self { Self(i as u16) }
 }
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 struct BitShift(u16);
 
 impl NodeId for BitShift {
+    fn usize(self) -> usize { self.0 as usize }
+    fn from_usize(i: usize) -> Self { Self(i as u16) }
+}
+
+impl FixedNodeId for BitShift {
+    const SPACE: usize = 1 << 15;
+
     fn new(arr: [u8; 3]) -> Self {
         let d0 = (arr[0] - b'A') as u16;
         let d1 = (arr[1] - b'A') as u16;
         let d2 = (arr[2] - b'A') as u16;
         Self(d2 << 10 | d1 << 5 | d0)
     }
+}
+
+/// A node ID assigned by `Interner` in order of first appearance.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Dense(u32);
 
-    fn u16(self) -> u16 { self.0 }
-    fn from_u16(i: u16) -> Self { Self(i) }
+impl NodeId for Dense {
     fn usize(self) -> usize { self.0 as usize }
-    fn from_usize(i: usize) -> Self { Self(i as u16) }
+    fn from_usize(i: usize) -> Self { Self(i as u32) }
+}
+
+#[derive(Default)]
+struct Interner<'t> {
+    ids:    HashMap<&'t str, Dense>,
+    labels: Vec<&'t str>,
+}
+
+impl<'t> Interner<'t> {
+    fn intern(&mut self, label: &'t str) -> Dense {
+        *self.ids.entry(label).or_insert_with(|| {
+            self.labels.push(label);
+            Dense::from_usize(self.labels.len() - 1)
+        })
+    }
+
+    fn get(&self, label: &str) -> Option<Dense> { self.ids.get(label).copied() }
+
+    fn labels(&self) -> impl Iterator<Item = (Dense, &'t str)> + '_ {
+        self.labels.iter().enumerate().map(|(i, &label)| (Dense::from_usize(i), label))
+    }
 }
 
 #[derive(Clone, Copy, Default)]
@@ -48,7 +90,7 @@ struct Node<NodeIdT: NodeId> {
     right: NodeIdT,
 }
 
-impl<NodeIdT: NodeId> Node<NodeIdT> {
+impl<NodeIdT: FixedNodeId> Node<NodeIdT> {
     fn parse(line: &str) -> (NodeIdT, Node<NodeIdT>) {
         let line = line.as_bytes();
         let src = NodeIdT::new(line[0..3].try_into().unwrap());
@@ -56,7 +98,9 @@ impl<NodeIdT: NodeId> Node<NodeIdT> {
         let right = NodeIdT::new(line[12..15].try_into().unwrap());
         (src, Node { left, right })
     }
+}
 
+impl<NodeIdT: NodeId> Node<NodeIdT> {
     fn go(self, dir: Dir) -> NodeIdT {
         match dir {
             Dir::Left => self.left,
@@ -65,6 +109,14 @@ impl<NodeIdT: NodeId> Node<NodeIdT> {
     }
 }
 
+/// Splits `src = (left, right)` into the three labels.
+fn parse_labels(line: &str) -> (&str, &str, &str) {
+    let (src, dests) = line.split_once(" = ").expect("has equal sign");
+    let dests = dests.strip_prefix('(').and_then(|s| s.strip_suffix(')')).expect("has parens");
+    let (left, right) = dests.split_once(", ").expect("has comma");
+    (src, left, right)
+}
+
 #[derive(Clone, Copy)]
 enum Dir {
     Left,
@@ -81,11 +133,11 @@ fn parse_steps(line: &str) -> impl Iterator<Item = Dir> + Clone + '_ {
         .cycle()
 }
 
-fn parse_graph<'t, NodeIdT: NodeId>(
+fn parse_graph<'t, NodeIdT: FixedNodeId>(
     lines: impl Iterator<Item = &'t str>,
     mut mark: impl FnMut(NodeIdT),
 ) -> Graph<NodeIdT> {
-    let mut nodes = [Node::default(); u16::MAX as usize];
+    let mut nodes = vec![Node::default(); NodeIdT::SPACE];
     for line in lines {
         if !line.is_empty() {
             let (src, node) = Node::<NodeIdT>::parse(line);
@@ -97,8 +149,24 @@ fn parse_graph<'t, NodeIdT: NodeId>(
     Graph { nodes }
 }
 
+fn parse_interned<'t>(lines: impl Iterator<Item = &'t str>) -> (Graph<Dense>, Interner<'t>) {
+    let mut interner = Interner::default();
+    let mut nodes = Vec::new();
+    for line in lines {
+        if !line.is_empty() {
+            let (src, left, right) = parse_labels(line);
+            let src = interner.intern(src);
+            let node = Node { left: interner.intern(left), right: interner.intern(right) };
+            nodes.resize(interner.labels.len().max(nodes.len()), Node::default());
+            nodes[src.usize()] = node;
+        }
+    }
+
+    (Graph { nodes }, interner)
+}
+
 struct Graph<NodeIdT: NodeId> {
-    nodes: [Node<NodeIdT>; u16::MAX as usize],
+    nodes: Vec<Node<NodeIdT>>,
 }
 
 impl<NodeIdT: NodeId> Graph<NodeIdT> {
@@ -120,16 +188,17 @@ impl<NodeIdT: NodeId> Graph<NodeIdT> {
     }
 }
 
-fn aaa<NodeIdT: NodeId>() -> NodeIdT { NodeIdT::new(*b"AAA") }
-fn aaz<NodeIdT: NodeId>() -> NodeIdT { NodeIdT::new(*b"AAZ") }
-fn zzz<NodeIdT: NodeId>() -> NodeIdT { NodeIdT::new(*b"ZZZ") }
+fn aaa<NodeIdT: FixedNodeId>() -> NodeIdT { NodeIdT::new(*b"AAA") }
+fn aaz<NodeIdT: FixedNodeId>() -> NodeIdT { NodeIdT::new(*b"AAZ") }
+fn zza<NodeIdT: FixedNodeId>() -> NodeIdT { NodeIdT::new(*b"ZZA") }
+fn zzz<NodeIdT: FixedNodeId>() -> NodeIdT { NodeIdT::new(*b"ZZZ") }
 
 #[aoc_runner_derive::aoc(day8, part1, Packed)]
 pub fn part1_packed(input: &str) -> u32 { part1::<Packed>(input) }
 #[aoc_runner_derive::aoc(day8, part1, BitShift)]
 pub fn part1_bitshift(input: &str) -> u32 { part1::<BitShift>(input) }
 
-fn part1<NodeIdT: NodeId>(input: &str) -> u32 {
+fn part1<NodeIdT: FixedNodeId>(input: &str) -> u32 {
     let mut lines = input.lines();
     let stepper = parse_steps(lines.next().unwrap());
     let graph: Graph<NodeIdT> = parse_graph(lines, |_| {});
@@ -137,35 +206,455 @@ fn part1<NodeIdT: NodeId>(input: &str) -> u32 {
     graph.count(aaa(), stepper, |node| node == zzz())
 }
 
+#[aoc_runner_derive::aoc(day8, part1, Interned)]
+pub fn part1_interned(input: &str) -> u32 {
+    let mut lines = input.lines();
+    let stepper = parse_steps(lines.next().unwrap());
+    let (graph, interner) = parse_interned(lines);
+
+    let zzz = interner.get("ZZZ").expect("no ZZZ node");
+    graph.count(interner.get("AAA").expect("no AAA node"), stepper, |node| node == zzz)
+}
+
 #[aoc_runner_derive::aoc(day8, part2, EmpiricalProd_Packed)]
 pub fn part2_packed(input: &str) -> u64 { part2::<Packed>(input) }
 #[aoc_runner_derive::aoc(day8, part2, EmpiricalProd_BitShift)]
 pub fn part2_bitshift(input: &str) -> u64 { part2::<BitShift>(input) }
 
-fn part2<NodeIdT: NodeId>(input: &str) -> u64 {
+fn part2<NodeIdT: FixedNodeId>(input: &str) -> u64 {
     let mut lines = input.lines();
 
-    let mut present: BitVec = BitVec::repeat(false, u16::MAX as usize);
+    let mut present: BitVec = BitVec::repeat(false, NodeIdT::SPACE);
 
     let steps_line = lines.next().unwrap();
     let stepper = parse_steps(steps_line);
     let graph: Graph<NodeIdT> = parse_graph(lines, |node: NodeIdT| present.set(node.usize(), true));
 
-    present[..676]
-        .iter_ones()
-        .map(|one| {
-            let count = graph.count(NodeIdT::from_usize(one), stepper.clone(), |node| {
-                node >= aaz() && node <= zzz()
-            });
-            (count as u64 / steps_line.len() as u64) as u64
+    // all labels ending with A have an ID not greater than ZZA
+    let starts = present[..=zza::<NodeIdT>().usize()].iter_ones().map(NodeIdT::from_usize);
+    empirical_prod(&graph, starts, stepper, steps_line.len(), |node| node >= aaz() && node <= zzz())
+}
+
+#[aoc_runner_derive::aoc(day8, part2, EmpiricalProd_Interned)]
+pub fn part2_interned(input: &str) -> u64 {
+    let mut lines = input.lines();
+
+    let steps_line = lines.next().unwrap();
+    let (graph, interner) = parse_interned(lines);
+    empirical_prod_interned(&graph, &interner, steps_line)
+}
+
+fn empirical_prod_interned(graph: &Graph<Dense>, interner: &Interner, steps_line: &str) -> u64 {
+    let ends: BitVec = interner.labels().map(|(_, label)| label.ends_with('Z')).collect();
+    let starts = interner.labels().filter(|(_, label)| label.ends_with('A')).map(|(id, _)| id);
+    empirical_prod(graph, starts, parse_steps(steps_line), steps_line.len(), |node| {
+        ends[node.usize()]
+    })
+}
+
+/// Assumes each ghost reaches its first Z node at a multiple of `steps_len`
+/// and that the cycle length is the same as this number of steps.
+///
+/// The result is silently wrong if these assumptions do not hold;
+/// `part2_strict` and `part2_verified` check them with `Analysis::check` first.
+fn empirical_prod<NodeIdT: NodeId>(
+    graph: &Graph<NodeIdT>,
+    starts: impl Iterator<Item = NodeIdT>,
+    stepper: impl Iterator<Item = Dir> + Clone,
+    steps_len: usize,
+    is_end: impl Fn(NodeIdT) -> bool,
+) -> u64 {
+    starts
+        .map(|start| {
+            let count = graph.count(start, stepper.clone(), &is_end);
+            count as u64 / steps_len as u64
         })
         .product::<u64>()
-        * steps_line.len() as u64
+        * steps_len as u64
+}
+
+/// Steps at which a ghost is on a Z node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hits {
+    /// hits before the ghost enters its cycle
+    pub prefix:      Vec<u64>,
+    /// the first step of the cycle in the (node, instruction index) state space
+    pub cycle_start: u64,
+    pub cycle_len:   u64,
+    /// hits in the first iteration of the cycle, repeating every `cycle_len` steps
+    pub cyclic:      Vec<u64>,
+}
+
+impl Hits {
+    fn find<NodeIdT: NodeId>(
+        graph: &Graph<NodeIdT>,
+        steps: &[Dir],
+        start: NodeIdT,
+        is_end: impl Fn(NodeIdT) -> bool,
+    ) -> Self {
+        const UNSEEN: u64 = u64::MAX;
+        let mut first_seen = vec![UNSEEN; graph.nodes.len() * steps.len()];
+
+        let mut hits = Vec::new();
+        let mut state = start;
+        for step in 0.. {
+            let phase = step as usize % steps.len();
+            let seen = &mut first_seen[state.usize() * steps.len() + phase];
+            if *seen != UNSEEN {
+                let cycle_start = *seen;
+                let cyclic = hits.split_off(hits.partition_point(|&hit| hit < cycle_start));
+                return Self { prefix: hits, cycle_start, cycle_len: step - cycle_start, cyclic };
+            }
+            *seen = step;
+
+            if is_end(state) {
+                hits.push(step);
+            }
+            state = graph.nodes[state.usize()].go(steps[phase]);
+        }
+
+        unreachable!()
+    }
+
+    fn contains(&self, step: u64) -> bool {
+        if step < self.cycle_start {
+            return self.prefix.contains(&step);
+        }
+        let phase = (step - self.cycle_start) % self.cycle_len;
+        self.cyclic.contains(&(self.cycle_start + phase))
+    }
+}
+
+/// Returns `(gcd(a, b), x, y)` such that `a * x + b * y = gcd(a, b)`.
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a % b);
+        (g, y, x - a / b * y)
+    }
+}
+
+/// Returns `a * b mod m` for `0 <= a, b < m`, even if `a * b` overflows.
+fn mul_mod(a: i128, b: i128, m: i128) -> i128 {
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+
+    // double and add, where every partial result is below m < 2^127
+    let (mut a, mut b, m) = (a as u128, b as u128, m as u128);
+    let mut product = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            product = (product + a) % m;
+        }
+        a = (a + a) % m;
+        b >>= 1;
+    }
+    product as i128
+}
+
+/// Solves `x = a1 (mod m1)` and `x = a2 (mod m2)` for moduli that are not necessarily coprime.
+///
+/// Returns `(x, lcm(m1, m2))` with `0 <= x < lcm(m1, m2)`, or `None` if there is no solution.
+fn crt(a1: i128, m1: i128, a2: i128, m2: i128) -> Result<Option<(i128, i128)>, CrtError> {
+    let (g, p, _) = ext_gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return Ok(None);
+    }
+    let lcm = (m1 / g).checked_mul(m2).ok_or(CrtError::Overflow)?;
+    let m = m2 / g;
+    let k = mul_mod(((a2 - a1) / g).rem_euclid(m), p.rem_euclid(m), m);
+    let x = m1.checked_mul(k).and_then(|x| x.checked_add(a1)).ok_or(CrtError::Overflow)?;
+    Ok(Some((x.rem_euclid(lcm), lcm)))
+}
+
+/// Upper bound on the number of hit combinations tracked by `first_common_hit`.
+const MAX_CONGRUENCES: usize = 1 << 20;
+
+/// The CRT solver could not determine the first common step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrtError {
+    /// The first common step does not fit in a `u64`,
+    /// or the combined cycle length does not fit in an `i128`.
+    Overflow,
+    /// More than `MAX_CONGRUENCES` combinations of Z hits would have to be checked.
+    TooManyCombinations,
+}
+
+impl fmt::Display for CrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow => write!(f, "the first common step overflows u64"),
+            Self::TooManyCombinations => {
+                write!(f, "more than {MAX_CONGRUENCES} combinations of Z hits to check")
+            }
+        }
+    }
+}
+
+impl error::Error for CrtError {}
+
+/// Returns the first step at which every ghost is on a Z node,
+/// or `None` if the ghosts are never on Z nodes at the same time.
+///
+/// Every combination of one cyclic hit per ghost is a separate congruence system,
+/// so the work grows with the product of the hit counts in the worst case.
+/// Combinations without a solution are dropped and equal congruences are merged as they are built;
+/// fails if more than `MAX_CONGRUENCES` remain.
+fn first_common_hit(ghosts: &[GhostReport]) -> Result<Option<u64>, CrtError> {
+    // a cyclic hit is never before the start of its ghost's cycle,
+    // while a common hit in the prefix of some ghost is before the start of that ghost's cycle
+    let prefix_best = ghosts
+        .iter()
+        .flat_map(|ghost| &ghost.hits.prefix)
+        .copied()
+        .filter(|&step| ghosts.iter().all(|ghost| ghost.hits.contains(step)))
+        .min();
+    if prefix_best.is_some() {
+        return Ok(prefix_best);
+    }
+
+    // (residue, modulus) -> smallest lower bound among the combinations leading to it;
+    // a smaller lower bound never gives a later step, so the others can be dropped
+    let mut congruences = HashMap::from([((0i128, 1i128), 0i128)]);
+    for GhostReport { hits: ghost, .. } in ghosts {
+        let cycle_len = ghost.cycle_len as i128;
+        let mut next = HashMap::new();
+        for (&(residue, modulus), &lower) in &congruences {
+            for &hit in &ghost.cyclic {
+                let hit = hit as i128;
+                if let Some(key) = crt(residue, modulus, hit % cycle_len, cycle_len)? {
+                    let entry = next.entry(key).or_insert(i128::MAX);
+                    *entry = (*entry).min(lower.max(hit));
+                    if next.len() > MAX_CONGRUENCES {
+                        return Err(CrtError::TooManyCombinations);
+                    }
+                }
+            }
+        }
+        congruences = next;
+    }
+
+    // a combination may overflow even if another one does not
+    let mut overflow = false;
+    let mut best = None;
+    for ((residue, modulus), lower) in congruences {
+        // lower < 2^64 and modulus < 2^127, so none of this overflows u128
+        let gap = (lower - residue).max(0) as u128;
+        let step = residue as u128 + gap.div_ceil(modulus as u128) * modulus as u128;
+        match u64::try_from(step) {
+            Ok(step) => best = Some(best.map_or(step, |best: u64| best.min(step))),
+            Err(_) => overflow = true,
+        }
+    }
+    match best {
+        None if overflow => Err(CrtError::Overflow),
+        best => Ok(best),
+    }
+}
+
+/// Combines the exact Z-hit pattern of every ghost with the generalized Chinese remainder theorem.
+///
+/// Returns `Ok(None)` if the ghosts are never on Z nodes at the same time.
+pub fn common_step(input: &str) -> Result<Option<u64>, CrtError> {
+    first_common_hit(&Analysis::parse(input).ghosts)
+}
+
+/// Runs `common_step`, reporting an input without a common step as an error.
+#[aoc_runner_derive::aoc(day8, part2, Crt)]
+pub fn part2_crt(input: &str) -> Result<u64, Box<dyn error::Error>> {
+    Ok(common_step(input)?.ok_or(NO_COMMON_STEP)?)
+}
+
+const NO_COMMON_STEP: &str = "the ghosts are never on Z nodes at the same time";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GhostReport {
+    /// label of the `..A` node the ghost starts from
+    pub start: String,
+    pub hits:  Hits,
+}
+
+/// The Z-hit pattern of every ghost, used to check the assumptions of `empirical_prod`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub steps_len: u64,
+    pub ghosts:    Vec<GhostReport>,
+}
+
+impl Analysis {
+    pub fn parse(input: &str) -> Self {
+        let mut lines = input.lines();
+
+        let steps_line = lines.next().unwrap();
+        let (graph, interner) = parse_interned(lines);
+        Self::new(&graph, &interner, steps_line)
+    }
+
+    fn new(graph: &Graph<Dense>, interner: &Interner, steps_line: &str) -> Self {
+        let steps: Vec<Dir> = parse_steps(steps_line).take(steps_line.len()).collect();
+        let ends: BitVec = interner.labels().map(|(_, label)| label.ends_with('Z')).collect();
+        let ghosts = interner
+            .labels()
+            .filter(|(_, label)| label.ends_with('A'))
+            .map(|(start, label)| GhostReport {
+                start: label.to_string(),
+                hits:  Hits::find(graph, &steps, start, |node| ends[node.usize()]),
+            })
+            .collect();
+
+        Self { steps_len: steps.len() as u64, ghosts }
+    }
+
+    /// Checks whether `empirical_prod` gives the correct answer for this input.
+    pub fn check(&self) -> Result<(), Violation> {
+        for GhostReport { start, hits } in &self.ghosts {
+            let start = start.clone();
+            if !hits.prefix.is_empty() {
+                return Err(Violation::PrefixHit { start });
+            }
+            if hits.cyclic.len() != 1 {
+                return Err(Violation::HitCount { start, count: hits.cyclic.len() });
+            }
+            if hits.cyclic[0] != hits.cycle_len {
+                return Err(Violation::Misaligned {
+                    start,
+                    hit: hits.cyclic[0],
+                    cycle_len: hits.cycle_len,
+                });
+            }
+            if hits.cycle_len % self.steps_len != 0 {
+                return Err(Violation::PartialInstructions { start, cycle_len: hits.cycle_len });
+            }
+        }
+
+        // the product of the quotients is only their LCM if they are pairwise coprime
+        for (i, first) in self.ghosts.iter().enumerate() {
+            for second in &self.ghosts[i + 1..] {
+                let (gcd, _, _) = ext_gcd(
+                    (first.hits.cycle_len / self.steps_len) as i128,
+                    (second.hits.cycle_len / self.steps_len) as i128,
+                );
+                if gcd != 1 {
+                    return Err(Violation::NotCoprime {
+                        first:  first.start.clone(),
+                        second: second.start.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for GhostReport { start, hits } in &self.ghosts {
+            writeln!(
+                f,
+                "{start}: cycle starts at step {}, length {}, Z at {:?} before the cycle and {:?} \
+                 in the cycle",
+                hits.cycle_start, hits.cycle_len, hits.prefix, hits.cyclic,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// An assumption of `empirical_prod` that does not hold for the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The ghost is on a Z node before entering its cycle.
+    PrefixHit { start: String },
+    /// The ghost is not on a Z node exactly once per cycle.
+    HitCount { start: String, count: usize },
+    /// The ghost is not on a Z node exactly at the end of the first cycle.
+    Misaligned { start: String, hit: u64, cycle_len: u64 },
+    /// The cycle is not a whole number of instruction passes.
+    PartialInstructions { start: String, cycle_len: u64 },
+    /// The numbers of instruction passes per cycle are not coprime.
+    NotCoprime { first: String, second: String },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PrefixHit { start } => write!(f, "{start} reaches Z before entering its cycle"),
+            Self::HitCount { start, count } => {
+                write!(f, "{start} reaches Z {count} times per cycle")
+            }
+            Self::Misaligned { start, hit, cycle_len } => {
+                write!(f, "{start} reaches Z at step {hit} instead of step {cycle_len}")
+            }
+            Self::PartialInstructions { start, cycle_len } => {
+                write!(f, "{start} has cycle length {cycle_len} not aligned to the instructions")
+            }
+            Self::NotCoprime { first, second } => {
+                write!(f, "{first} and {second} have cycle lengths with a common factor")
+            }
+        }
+    }
+}
+
+impl error::Error for Violation {}
+
+/// Parses `input` and only runs `empirical_prod` if `Analysis::check` passes.
+fn run_checked(input: &str) -> (Analysis, Result<u64, Violation>) {
+    let mut lines = input.lines();
+
+    let steps_line = lines.next().unwrap();
+    let (graph, interner) = parse_interned(lines);
+    let analysis = Analysis::new(&graph, &interner, steps_line);
+    let result = analysis.check().map(|()| empirical_prod_interned(&graph, &interner, steps_line));
+    (analysis, result)
+}
+
+/// Refuses to answer with `empirical_prod` if `Analysis::check` fails,
+/// returning the violated assumption instead.
+#[aoc_runner_derive::aoc(day8, part2, Strict)]
+pub fn part2_strict(input: &str) -> Result<u64, Violation> { run_checked(input).1 }
+
+/// The answer of `part2_verified`, and which solver produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verified {
+    /// The assumptions hold, so `empirical_prod` is correct.
+    Empirical(u64),
+    /// `empirical_prod` was rejected because of `violation`, so the CRT solver was used.
+    Crt { step: u64, violation: Violation },
+}
+
+impl fmt::Display for Verified {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empirical(step) => write!(f, "{step}"),
+            Self::Crt { step, violation } => {
+                write!(f, "{step} (empirical product rejected: {violation})")
+            }
+        }
+    }
+}
+
+/// Only runs `empirical_prod` if `Analysis::check` passes,
+/// falling back to the CRT solver otherwise.
+#[aoc_runner_derive::aoc(day8, part2, Verified)]
+pub fn part2_verified(input: &str) -> Result<Verified, Box<dyn error::Error>> {
+    let (analysis, result) = run_checked(input);
+    match result {
+        Ok(step) => Ok(Verified::Empirical(step)),
+        Err(violation) => match first_common_hit(&analysis.ghosts) {
+            Ok(Some(step)) => Ok(Verified::Crt { step, violation }),
+            Ok(None) => Err(format!("{violation}, and {NO_COMMON_STEP}").into()),
+            Err(err) => Err(format!("{violation}, and {err}").into()),
+        },
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::day8::{BitShift, Packed};
+    use crate::day8::{BitShift, NodeId, Packed};
+    use crate::test_util::Xorshift;
 
     const SAMPLE1: &str = r"LLR
 
@@ -173,6 +662,47 @@ AAA = (BBB, BBB)
 BBB = (AAA, ZZZ)
 ZZZ = (ZZZ, ZZZ)";
 
+    /// Each ghost reaches its first Z node after exactly one cycle.
+    const SAMPLE_ALIGNED: &str = r"L
+
+CCA = (CCB, CCB)
+CCB = (CCZ, CCZ)
+CCZ = (CCB, CCB)
+DDA = (DDB, DDB)
+DDB = (DDC, DDC)
+DDC = (DDZ, DDZ)
+DDZ = (DDB, DDB)";
+
+    const SAMPLE2: &str = r"LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)";
+
+    /// One ghost is on Z nodes at odd steps only, the other at even steps only.
+    const SAMPLE_DISJOINT: &str = r"L
+
+1A = (1Z, 1Z)
+1Z = (1B, 1B)
+1B = (1Z, 1Z)
+2A = (2B, 2B)
+2B = (2Z, 2Z)
+2Z = (2B, 2B)";
+
+    /// The first ghost is only on a Z node before entering its cycle.
+    const SAMPLE_PREFIX: &str = r"L
+
+1A = (1Z, 1Z)
+1Z = (1B, 1B)
+1B = (1B, 1B)
+2A = (2Z, 2Z)
+2Z = (2Z, 2Z)";
+
     #[test]
     fn test_part1_packed() {
         assert_eq!(super::part1::<Packed>(SAMPLE1), 6);
@@ -181,4 +711,232 @@ ZZZ = (ZZZ, ZZZ)";
     fn test_part1_bitshift() {
         assert_eq!(super::part1::<BitShift>(SAMPLE1), 6);
     }
+    #[test]
+    fn test_part1_interned() {
+        assert_eq!(super::part1_interned(SAMPLE1), 6);
+    }
+
+    #[test]
+    fn test_part2_packed() {
+        assert_eq!(super::part2::<Packed>(SAMPLE_ALIGNED), 6);
+    }
+    #[test]
+    fn test_part2_bitshift() {
+        assert_eq!(super::part2::<BitShift>(SAMPLE_ALIGNED), 6);
+    }
+    #[test]
+    fn test_part2_interned() {
+        assert_eq!(super::part2_interned(SAMPLE_ALIGNED), 6);
+        assert_eq!(super::part2_interned(&SAMPLE_ALIGNED.replace("CC", "1").replace("DD", "2")), 6);
+    }
+
+    #[test]
+    fn test_part2_crt() {
+        assert_eq!(super::common_step(SAMPLE2), Ok(Some(6)));
+        assert_eq!(super::common_step(SAMPLE_ALIGNED), Ok(Some(6)));
+        assert_eq!(super::common_step(SAMPLE_DISJOINT), Ok(None));
+        assert_eq!(super::common_step(SAMPLE_PREFIX), Ok(Some(1)));
+
+        assert_eq!(super::part2_crt(SAMPLE2).unwrap(), 6);
+        assert_eq!(
+            super::part2_crt(SAMPLE_DISJOINT).unwrap_err().to_string(),
+            "the ghosts are never on Z nodes at the same time"
+        );
+    }
+
+    /// Ghosts that run through a single loop of each length, on a Z node once per loop.
+    fn loops(lens: &[u64]) -> String {
+        let mut input = String::from("L\n\n");
+        for (ghost, &len) in lens.iter().enumerate() {
+            let node = |i: u64| match i {
+                0 => format!("{ghost}A"),
+                _ if i == len => format!("{ghost}Z"),
+                _ => format!("{ghost}N{i}"),
+            };
+            for i in 0..len {
+                input.push_str(&format!("{} = ({next}, {next})\n", node(i), next = node(i + 1)));
+            }
+            input.push_str(&format!("{} = ({next}, {next})\n", node(len), next = node(1)));
+        }
+        input
+    }
+
+    #[test]
+    fn test_common_step_overflow() {
+        use super::CrtError;
+
+        assert_eq!(super::common_step(&loops(&[3, 5, 7])), Ok(Some(105)));
+
+        // the first common step is 51137716647782649273
+        let input = loops(&[3, 7001, 7013, 7019, 7027, 7039]);
+        assert_eq!(super::common_step(&input), Err(CrtError::Overflow));
+        assert_eq!(
+            super::part2_crt(&input).unwrap_err().to_string(),
+            "the first common step overflows u64"
+        );
+
+        // the moduli overflow i128 before any step is computed
+        let primes =
+            [1_000_003u64, 1_000_033, 1_000_037, 1_000_039, 1_000_081, 1_000_099, 1_000_117];
+        let ghosts: Vec<_> = primes.iter().map(|&len| ghost(len, vec![len - 1])).collect();
+        assert_eq!(super::first_common_hit(&ghosts), Err(CrtError::Overflow));
+    }
+
+    fn ghost(cycle_len: u64, cyclic: Vec<u64>) -> super::GhostReport {
+        super::GhostReport {
+            start: cycle_len.to_string(),
+            hits:  super::Hits { prefix: Vec::new(), cycle_start: 0, cycle_len, cyclic },
+        }
+    }
+
+    #[test]
+    fn test_first_common_hit_too_many_combinations() {
+        use super::{first_common_hit, CrtError};
+
+        // every combination of hits is a distinct congruence
+        let ghosts: Vec<_> = [37, 41, 43, 47].map(|len| ghost(len, (1..len).collect())).into();
+        assert_eq!(first_common_hit(&ghosts), Err(CrtError::TooManyCombinations));
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(super::crt(2, 4, 4, 6), Ok(Some((10, 12))));
+        assert_eq!(super::crt(1, 4, 2, 6), Ok(None));
+        assert_eq!(super::crt(3, 5, 0, 1), Ok(Some((3, 5))));
+
+        // (a2 - a1) / g * p alone would overflow i128, although the solution does not
+        let m2 = (1i128 << 124) + 1;
+        let (x, lcm) = super::crt(1, 3, m2 - 1, m2).unwrap().unwrap();
+        assert_eq!((x % 3, x % m2, lcm), (1, m2 - 1, 3 * m2));
+    }
+
+    #[test]
+    fn test_first_common_hit_merges_combinations() {
+        use super::{first_common_hit, GhostReport, Hits};
+
+        // 2^20 combinations of hits, but only two distinct congruences mod 4
+        let ghost = |start: usize| GhostReport {
+            start: start.to_string(),
+            hits:  Hits {
+                prefix:      Vec::new(),
+                cycle_start: 0,
+                cycle_len:   4,
+                cyclic:      vec![1, 3],
+            },
+        };
+        let ghosts: Vec<_> = (0..20).map(ghost).collect();
+        assert_eq!(first_common_hit(&ghosts), Ok(Some(1)));
+    }
+
+    #[test]
+    fn test_part2_crt_matches_simulation() {
+        const LIMIT: u64 = 10_000;
+
+        let mut rng = Xorshift::new(8);
+        for _ in 0..300 {
+            let node_count = rng.below(8) as usize + 2;
+            let labels: Vec<String> = (0..node_count)
+                .map(|i| format!("{i}{}", ['A', 'B', 'Z'][rng.below(3) as usize]))
+                .collect();
+
+            let mut input: String =
+                (0..rng.below(4) + 1).map(|_| if rng.below(2) == 0 { 'L' } else { 'R' }).collect();
+            input.push_str("\n\n");
+            for label in &labels {
+                let left = &labels[rng.below(node_count as u64) as usize];
+                let right = &labels[rng.below(node_count as u64) as usize];
+                input.push_str(&format!("{label} = ({left}, {right})\n"));
+            }
+
+            let mut lines = input.lines();
+            let steps = super::parse_steps(lines.next().unwrap());
+            let (graph, interner) = super::parse_interned(lines);
+            let mut ghosts: Vec<_> = interner
+                .labels()
+                .filter(|(_, label)| label.ends_with('A'))
+                .map(|(id, _)| id)
+                .collect();
+            let simulated = (0..LIMIT).zip(steps).find_map(|(step, dir)| {
+                if ghosts.iter().all(|&ghost| interner.labels[ghost.usize()].ends_with('Z')) {
+                    return Some(step);
+                }
+                for ghost in &mut ghosts {
+                    *ghost = graph.nodes[ghost.usize()].go(dir);
+                }
+                None
+            });
+
+            match super::common_step(&input).unwrap() {
+                Some(step) if step < LIMIT => assert_eq!(simulated, Some(step), "{input}"),
+                _ => assert_eq!(simulated, None, "{input}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_analysis() {
+        let analysis = super::Analysis::parse(SAMPLE2);
+        assert_eq!(
+            analysis.to_string(),
+            "11A: cycle starts at step 1, length 2, Z at [] before the cycle and [2] in the \
+             cycle\n22A: cycle starts at step 1, length 6, Z at [] before the cycle and [3, 6] in \
+             the cycle\n"
+        );
+        assert_eq!(
+            analysis.check(),
+            Err(super::Violation::HitCount { start: "22A".into(), count: 2 })
+        );
+
+        assert_eq!(super::Analysis::parse(SAMPLE_ALIGNED).check(), Ok(()));
+        assert!(super::Analysis::parse(SAMPLE_PREFIX).check().is_err());
+    }
+
+    #[test]
+    fn test_part2_strict() {
+        use super::Violation;
+
+        assert_eq!(super::part2_strict(SAMPLE_ALIGNED), Ok(6));
+        assert_eq!(
+            super::part2_strict(SAMPLE2),
+            Err(Violation::HitCount { start: "22A".into(), count: 2 })
+        );
+        assert_eq!(
+            super::part2_strict(SAMPLE_PREFIX),
+            Err(Violation::PrefixHit { start: "1A".into() })
+        );
+    }
+
+    #[test]
+    fn test_part2_verified() {
+        use super::{Verified, Violation};
+
+        assert_eq!(super::part2_verified(SAMPLE_ALIGNED).unwrap(), Verified::Empirical(6));
+        assert_eq!(super::part2_verified(SAMPLE_ALIGNED).unwrap().to_string(), "6");
+
+        let verified = super::part2_verified(SAMPLE2).unwrap();
+        assert_eq!(
+            verified,
+            Verified::Crt {
+                step:      6,
+                violation: Violation::HitCount { start: "22A".into(), count: 2 },
+            }
+        );
+        assert_eq!(
+            verified.to_string(),
+            "6 (empirical product rejected: 22A reaches Z 2 times per cycle)"
+        );
+
+        assert_eq!(
+            super::part2_verified(SAMPLE_DISJOINT).unwrap_err().to_string(),
+            "1A reaches Z at step 1 instead of step 2, and the ghosts are never on Z nodes at the \
+             same time"
+        );
+
+        // Z is hit at odd steps and at steps 2 mod 3, so the empirical product 1 * 2 is wrong
+        let offset =
+            "L\n\n1A = (1Z, 1Z)\n1Z = (1A, 1A)\n2A = (2B, 2B)\n2B = (2Z, 2Z)\n2Z = (2A, 2A)";
+        assert_eq!(super::part2_interned(offset), 2);
+        assert!(super::part2_strict(offset).is_err());
+        assert!(matches!(super::part2_verified(offset).unwrap(), Verified::Crt { step: 5, .. }));
+    }
 }
@@ -1,4 +1,8 @@
-use std::mem;
+use std::{iter, mem};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Zero;
 
 type InputSize = i32;
 type ComputeSize = i64;
@@ -10,7 +14,7 @@ struct FactorialSlice([[ComputeSize; FACTORIAL_MAX_WIDTH]; FACTORIAL_SIZE]);
 impl Default for FactorialSlice {
     fn default() -> Self {
         let mut result = [[0; FACTORIAL_MAX_WIDTH]; FACTORIAL_SIZE];
-        for i in 1..FACTORIAL_SIZE {
+        for i in 0..FACTORIAL_SIZE {
             let mut prod = 1 as ComputeSize;
             for j in (i + 1)..(i + FACTORIAL_MAX_WIDTH).min(FACTORIAL_SIZE) {
                 prod *= j as ComputeSize;
@@ -34,7 +38,7 @@ impl FactorialSlice {
         let ac = if a == c { 1 } else { self.0[c][a - c] };
         let b_fact = if b <= 1 { 1 } else { self.0[1][b - 1] };
 
-        return ac / b_fact;
+        ac / b_fact
     }
 }
 
@@ -49,7 +53,7 @@ fn solve_1(fact: &FactorialSlice, line: &[InputSize]) -> ComputeSize {
         .map(|(i, &item)| {
             let mut coef =
                 fact.get(line.len(), i, line.len() - i - 1) / ((line.len() - i) as ComputeSize);
-            if (line.len() - i) % 2 == 0 {
+            if (line.len() - i).is_multiple_of(2) {
                 coef *= -1;
             }
             coef * item as ComputeSize
@@ -74,18 +78,13 @@ fn solve_2(fact: &FactorialSlice, line: &[InputSize]) -> ComputeSize {
         .sum::<ComputeSize>() as ComputeSize
 }
 
-fn solve(
-    solver: impl Fn(&FactorialSlice, &[InputSize]) -> ComputeSize,
-    input: &[u8],
-) -> ComputeSize {
-    let fact = FactorialSlice::default();
-
-    let mut output = 0;
-
+/// Calls `f` with the values of each line in `input`.
+fn parse_lines(input: &[u8], mut f: impl FnMut(&[InputSize])) {
     let mut line: Vec<InputSize> = Vec::new();
     let mut current = 0;
     let mut negative = false;
 
+    let input = input.strip_suffix(b"\n").unwrap_or(input);
     for &byte in input.iter().chain(b"\n") {
         match byte {
             b'-' => negative = true,
@@ -95,8 +94,7 @@ fn solve(
                 negative = false;
 
                 if byte == b'\n' {
-                    let result = solver(&fact, &line);
-                    output += result;
+                    f(&line);
                     line.clear();
                 }
             }
@@ -112,19 +110,289 @@ fn solve(
             _ => unreachable!(),
         }
     }
+}
 
+fn solve(
+    solver: impl Fn(&FactorialSlice, &[InputSize]) -> ComputeSize,
+    input: &[u8],
+) -> ComputeSize {
+    let fact = FactorialSlice::default();
+    sum_lines(input, |line| solver(&fact, line))
+}
+
+fn sum_lines(input: &[u8], mut solver: impl FnMut(&[InputSize]) -> ComputeSize) -> ComputeSize {
+    let mut output = 0;
+    parse_lines(input, |line| output += solver(line));
     output
 }
 
-#[aoc_runner_derive::aoc(day9, part1)]
+#[aoc_runner_derive::aoc(day9, part1, Lagrange)]
 pub fn part1(input: &[u8]) -> ComputeSize { solve(solve_1, input) }
 
-#[aoc_runner_derive::aoc(day9, part2)]
+#[aoc_runner_derive::aoc(day9, part2, Lagrange)]
 pub fn part2(input: &[u8]) -> ComputeSize { solve(solve_2, input) }
 
+/// Reference implementation by repeated differencing until all differences are zero.
+///
+/// Returns the extrapolated values `(P(-1), P(n))`.
+fn solve_differences(line: &[InputSize]) -> (ComputeSize, ComputeSize) {
+    let mut row: Vec<ComputeSize> = line.iter().map(|&item| item as ComputeSize).collect();
+    let mut firsts = Vec::new();
+    let mut next = 0;
+
+    while row.iter().any(|&item| item != 0) {
+        firsts.push(row[0]);
+        next += row[row.len() - 1];
+        row = row.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    }
+
+    let prev = firsts.iter().rev().fold(0, |below, &first| first - below);
+    (prev, next)
+}
+
+#[aoc_runner_derive::aoc(day9, part1, FiniteDifference)]
+pub fn part1_differences(input: &[u8]) -> ComputeSize {
+    sum_lines(input, |line| solve_differences(line).1)
+}
+
+#[aoc_runner_derive::aoc(day9, part2, FiniteDifference)]
+pub fn part2_differences(input: &[u8]) -> ComputeSize {
+    sum_lines(input, |line| solve_differences(line).0)
+}
+
+/// Rows of Pascal's triangle, where `self.0[n][j]` is `C(n, j)`.
+#[derive(Default)]
+struct BinomialRows(Vec<Vec<ComputeSize>>);
+
+impl BinomialRows {
+    fn row(&mut self, n: usize) -> &[ComputeSize] {
+        while self.0.len() <= n {
+            let next = match self.0.last() {
+                None => vec![1],
+                Some(prev) => iter::once(1)
+                    .chain(prev.windows(2).map(|pair| pair[0] + pair[1]))
+                    .chain(iter::once(1))
+                    .collect(),
+            };
+            self.0.push(next);
+        }
+        &self.0[n]
+    }
+}
+
+fn solve_rows_1(rows: &mut BinomialRows, line: &[InputSize]) -> ComputeSize {
+    // The Lagrange coefficients at n simplify to
+    // P(n) = sum( (-1)^(n-1-i) * C(n, i) * line[i] for i in 0..n )
+
+    let n = line.len();
+    let row = rows.row(n);
+    line.iter()
+        .enumerate()
+        .map(|(i, &item)| {
+            let coef = if (n - 1 - i) % 2 == 1 { -row[i] } else { row[i] };
+            coef * item as ComputeSize
+        })
+        .sum()
+}
+
+fn solve_rows_2(rows: &mut BinomialRows, line: &[InputSize]) -> ComputeSize {
+    // Similarly, P(-1) = sum( (-1)^i * C(n, i+1) * line[i] for i in 0..n )
+
+    let row = rows.row(line.len());
+    line.iter()
+        .enumerate()
+        .map(|(i, &item)| {
+            let coef = if i % 2 == 1 { -row[i + 1] } else { row[i + 1] };
+            coef * item as ComputeSize
+        })
+        .sum()
+}
+
+#[aoc_runner_derive::aoc(day9, part1, BinomialRows)]
+pub fn part1_rows(input: &[u8]) -> ComputeSize {
+    let mut rows = BinomialRows::default();
+    sum_lines(input, |line| solve_rows_1(&mut rows, line))
+}
+
+#[aoc_runner_derive::aoc(day9, part2, BinomialRows)]
+pub fn part2_rows(input: &[u8]) -> ComputeSize {
+    let mut rows = BinomialRows::default();
+    sum_lines(input, |line| solve_rows_2(&mut rows, line))
+}
+
+/// Returns the generalized binomial coefficient `C(n, r)` for any integer `n`,
+/// or `None` if it overflows.
+fn binomial_i128(n: i128, r: u32) -> Option<i128> {
+    if n < 0 {
+        // C(n, r) = (-1)^r * C(r - n - 1, r)
+        let positive = binomial_i128(r as i128 - n - 1, r)?;
+        return Some(if r.is_multiple_of(2) { positive } else { -positive });
+    }
+    if (r as i128) > n {
+        return Some(0);
+    }
+
+    let r = (r as i128).min(n - r as i128);
+    let mut output: i128 = 1;
+    for t in 0..r {
+        // the product of t + 1 consecutive integers is divisible by (t + 1)!
+        output = output.checked_mul(n - t)? / (t + 1);
+    }
+    Some(output)
+}
+
+fn binomial_big(n: i128, r: u32) -> BigInt {
+    if n < 0 {
+        let positive = binomial_big(r as i128 - n - 1, r);
+        return if r.is_multiple_of(2) { positive } else { -positive };
+    }
+    if (r as i128) > n {
+        return BigInt::zero();
+    }
+
+    let r = (r as i128).min(n - r as i128);
+    let mut output = BigInt::from(1);
+    for t in 0..r {
+        output = output * (n - t) / (t + 1);
+    }
+    output
+}
+
+// The Lagrange coefficient of `line[i]` when evaluating at `k` is
+// (-1)^(n-1-i) * C(k, i) * C(k-i-1, n-1-i), where n = line.len().
+
+fn extrapolate_i128(line: &[InputSize], k: i128) -> Option<i128> {
+    let n = line.len();
+    let mut output: i128 = 0;
+    for (i, &item) in line.iter().enumerate() {
+        let left = binomial_i128(k, i as u32)?;
+        let right = binomial_i128(k - i as i128 - 1, (n - 1 - i) as u32)?;
+        let term = left.checked_mul(right)?.checked_mul(item as i128)?;
+        output = if (n - 1 - i) % 2 == 1 {
+            output.checked_sub(term)?
+        } else {
+            output.checked_add(term)?
+        };
+    }
+    Some(output)
+}
+
+fn extrapolate_big(line: &[InputSize], k: i128) -> BigInt {
+    let n = line.len();
+    let mut output = BigInt::zero();
+    for (i, &item) in line.iter().enumerate() {
+        let left = binomial_big(k, i as u32);
+        let right = binomial_big(k - i as i128 - 1, (n - 1 - i) as u32);
+        let term = left * right * item;
+        if (n - 1 - i) % 2 == 1 {
+            output -= term;
+        } else {
+            output += term;
+        }
+    }
+    output
+}
+
+/// Evaluates at index `k` the polynomial of minimal degree passing through `(i, line[i])`.
+///
+/// Uses checked `i128` arithmetic and falls back to exact big integers on overflow,
+/// so it works for lines of any length and any `k`.
+pub fn extrapolate(line: &[InputSize], k: i128) -> BigInt {
+    match extrapolate_i128(line, k) {
+        Some(value) => value.into(),
+        None => extrapolate_big(line, k),
+    }
+}
+
+fn solve_exact(input: &[u8], target: impl Fn(usize) -> i128) -> BigInt {
+    let mut output = BigInt::zero();
+    parse_lines(input, |line| output += extrapolate(line, target(line.len())));
+    output
+}
+
+#[aoc_runner_derive::aoc(day9, part1, Exact)]
+pub fn part1_exact(input: &[u8]) -> BigInt { solve_exact(input, |len| len as i128) }
+
+#[aoc_runner_derive::aoc(day9, part2, Exact)]
+pub fn part2_exact(input: &[u8]) -> BigInt { solve_exact(input, |_| -1) }
+
+/// The polynomial of minimal degree passing through `(i, line[i])`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fit {
+    /// 0 for constant lines, including all-zero lines
+    pub degree:       usize,
+    /// exact coefficients in ascending order of power, with length `degree + 1`
+    pub coefficients: Vec<BigRational>,
+    /// whether the differences reach an all-zero row,
+    /// i.e. the line has more values than a polynomial of this degree needs
+    pub determined:   bool,
+}
+
+impl Fit {
+    pub fn eval(&self, x: &BigRational) -> BigRational {
+        self.coefficients.iter().rev().fold(BigRational::zero(), |acc, coef| acc * x + coef)
+    }
+}
+
+/// Fits the line with Newton's forward differences
+/// `P(x) = sum( diff[j] * x(x-1)...(x-j+1) / j! for j in 0..n )`,
+/// where `diff[j]` is the first value of the `j`-th difference row.
+pub fn fit(line: &[InputSize]) -> Fit {
+    let mut row: Vec<BigInt> = line.iter().map(|&item| BigInt::from(item)).collect();
+    let mut diffs = Vec::with_capacity(line.len());
+    while let Some(first) = row.first() {
+        diffs.push(first.clone());
+        row = row.windows(2).map(|pair| &pair[1] - &pair[0]).collect();
+    }
+
+    let degree = diffs.iter().rposition(|diff| !diff.is_zero()).unwrap_or(0);
+    let determined = degree + 1 < line.len();
+
+    let mut coefficients = vec![BigRational::zero(); degree + 1];
+    // coefficients of the falling factorial x(x-1)...(x-j+1)
+    let mut falling = vec![BigInt::from(1)];
+    let mut factorial = BigInt::from(1);
+    for (j, diff) in diffs.iter().take(degree + 1).enumerate() {
+        if j > 0 {
+            factorial *= j;
+            // multiply by (x - (j - 1))
+            let mut next = vec![BigInt::zero(); falling.len() + 1];
+            for (power, coef) in falling.iter().enumerate() {
+                next[power + 1] += coef;
+                next[power] -= coef * (j - 1);
+            }
+            falling = next;
+        }
+        for (power, coef) in falling.iter().enumerate() {
+            coefficients[power] += BigRational::new(coef * diff, factorial.clone());
+        }
+    }
+
+    Fit { degree, coefficients, determined }
+}
+
+/// Returns the zero-based indices of lines that do not reach an all-zero difference row,
+/// for which extrapolation is not meaningful.
+pub fn undetermined_lines(input: &[u8]) -> Vec<usize> {
+    let mut output = Vec::new();
+    let mut index = 0;
+    parse_lines(input, |line| {
+        if !fit(line).determined {
+            output.push(index);
+        }
+        index += 1;
+    });
+    output
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{ComputeSize, FactorialSlice};
+    use num_bigint::BigInt;
+    use num_rational::BigRational;
+    use num_traits::Zero;
+
+    use super::{ComputeSize, FactorialSlice, InputSize};
+    use crate::test_util::Xorshift;
 
     const SAMPLE: &[u8] = b"0 3 6 9 12 15
 1 3 6 10 15 21
@@ -156,4 +424,159 @@ mod tests {
     fn test_part2() {
         assert_eq!(super::part2(SAMPLE), 2);
     }
+
+    #[test]
+    fn test_single_value() {
+        // a single value is a constant polynomial
+        assert_eq!(super::part1(b"7"), 7);
+        assert_eq!(super::part2(b"7"), 7);
+        assert_eq!(super::part1(b"-3\n5"), 2);
+    }
+
+    #[test]
+    fn test_part1_differences() {
+        assert_eq!(super::part1_differences(SAMPLE), 18 + 28 + 68);
+    }
+
+    #[test]
+    fn test_part2_differences() {
+        assert_eq!(super::part2_differences(SAMPLE), 2);
+    }
+
+    #[test]
+    fn test_part1_rows() {
+        assert_eq!(super::part1_rows(SAMPLE), 18 + 28 + 68);
+    }
+
+    #[test]
+    fn test_part2_rows() {
+        assert_eq!(super::part2_rows(SAMPLE), 2);
+    }
+
+    #[test]
+    fn test_variants_agree() {
+        let mut rng = Xorshift::new(9);
+        for _ in 0..50 {
+            let mut input = String::new();
+            for _ in 0..10 {
+                let len = rng.below(21) + 1;
+                let line: Vec<String> =
+                    (0..len).map(|_| (rng.below(2001) as i64 - 1000).to_string()).collect();
+                input.push_str(&line.join(" "));
+                input.push('\n');
+            }
+            let input = input.trim_end().as_bytes();
+
+            let part1 = super::part1(input);
+            assert_eq!(super::part1_differences(input), part1);
+            assert_eq!(super::part1_rows(input), part1);
+            assert_eq!(super::part1_exact(input), BigInt::from(part1));
+
+            let part2 = super::part2(input);
+            assert_eq!(super::part2_differences(input), part2);
+            assert_eq!(super::part2_rows(input), part2);
+            assert_eq!(super::part2_exact(input), BigInt::from(part2));
+        }
+    }
+
+    #[test]
+    fn test_part1_exact() {
+        assert_eq!(super::part1_exact(SAMPLE), BigInt::from(18 + 28 + 68));
+    }
+
+    #[test]
+    fn test_part2_exact() {
+        assert_eq!(super::part2_exact(SAMPLE), BigInt::from(2));
+    }
+
+    #[test]
+    fn test_binomial() {
+        assert_eq!(super::binomial_i128(5, 2), Some(10));
+        assert_eq!(super::binomial_i128(2, 5), Some(0));
+        assert_eq!(super::binomial_i128(-3, 2), Some(6));
+        assert_eq!(super::binomial_i128(-3, 3), Some(-10));
+        assert_eq!(super::binomial_i128(200, 100), None);
+        assert_eq!(super::binomial_big(-3, 3), BigInt::from(-10));
+        assert_eq!(
+            super::binomial_big(200, 100).to_string(),
+            "90548514656103281165404177077484163874504589675413336841320"
+        );
+    }
+
+    #[test]
+    fn test_extrapolate_any_target() {
+        let poly = |x: i128| x * x * x - 7 * x + 3;
+        let line: Vec<InputSize> = (0..40).map(|x| poly(x) as InputSize).collect();
+        for k in [-50, -1, 0, 17, 40, 41, 1000] {
+            assert_eq!(super::extrapolate(&line, k), BigInt::from(poly(k)), "k = {k}");
+        }
+    }
+
+    #[test]
+    fn test_extrapolate_overflow() {
+        // the interpolating polynomial of an exponential sequence has huge coefficients
+        let line: Vec<InputSize> = (0..30).map(|i| 1 << i).collect();
+        assert_eq!(super::extrapolate_i128(&line, 1000), None);
+
+        // reference by repeated differencing
+        let reference = |line: &[InputSize], forward: bool| {
+            let mut rows = vec![line.iter().map(|&x| BigInt::from(x)).collect::<Vec<_>>()];
+            while rows.last().unwrap().iter().any(|x| *x != BigInt::zero()) {
+                let row = rows.last().unwrap();
+                rows.push(row.windows(2).map(|pair| &pair[1] - &pair[0]).collect());
+            }
+            rows.iter().rev().fold(BigInt::zero(), |acc, row| {
+                if forward {
+                    row.last().cloned().unwrap_or_default() + acc
+                } else {
+                    row.first().cloned().unwrap_or_default() - acc
+                }
+            })
+        };
+        assert_eq!(super::extrapolate(&line, 30), reference(&line, true));
+        assert_eq!(super::extrapolate(&line, -1), reference(&line, false));
+        assert_eq!(super::extrapolate(&line, 30), BigInt::from((1i64 << 30) - 1));
+    }
+
+    #[test]
+    fn test_fit() {
+        let ratio = |numer: i64, denom: i64| BigRational::new(numer.into(), denom.into());
+
+        // triangular numbers (x^2 + 3x + 2) / 2
+        let fit = super::fit(&[1, 3, 6, 10, 15, 21]);
+        assert_eq!(fit.degree, 2);
+        assert_eq!(fit.coefficients, [ratio(1, 1), ratio(3, 2), ratio(1, 2)]);
+        assert!(fit.determined);
+
+        let fit = super::fit(&[0, 0, 0]);
+        assert_eq!((fit.degree, fit.coefficients, fit.determined), (0, vec![ratio(0, 1)], true));
+
+        let fit = super::fit(&[4]);
+        assert_eq!((fit.degree, fit.coefficients, fit.determined), (0, vec![ratio(4, 1)], false));
+
+        // three points determine a parabola, but nothing confirms it
+        let fit = super::fit(&[1, 2, 4]);
+        assert_eq!(fit.degree, 2);
+        assert!(!fit.determined);
+    }
+
+    #[test]
+    fn test_fit_matches_extrapolate() {
+        let mut rng = Xorshift::new(35);
+        for _ in 0..100 {
+            let line: Vec<InputSize> =
+                (0..rng.below(15) + 1).map(|_| rng.below(201) as InputSize - 100).collect();
+            let fit = super::fit(&line);
+            for k in [-3, -1, 0, line.len() as i128, 30] {
+                let expected = BigRational::from(super::extrapolate(&line, k));
+                assert_eq!(fit.eval(&BigRational::from(BigInt::from(k))), expected, "{line:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_undetermined_lines() {
+        assert_eq!(super::undetermined_lines(SAMPLE), Vec::<usize>::new());
+        assert_eq!(super::undetermined_lines(b"1 2 3\n1 2 4\n5"), [1, 2]);
+    }
 }
@@ -0,0 +1,432 @@
+//! Half-open intervals, interval sets and piecewise offset maps over integers.
+//!
+//! Since every interval excludes its end, no interval can contain `T::max_value()`.
+//! The checked constructors reject intervals and pieces that would need it,
+//! and maps always send `T::max_value()` to itself.
+
+use std::{fmt, iter};
+
+use num_traits::PrimInt;
+
+/// The half-open interval `start..end`.
+///
+/// An interval with `start >= end` is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end:   T,
+}
+
+impl<T: PrimInt> Interval<T> {
+    pub fn new(start: T, end: T) -> Self { Self { start, end } }
+
+    pub fn with_width(start: T, width: T) -> Self { Self { start, end: start + width } }
+
+    /// Like `with_width`, but fails if the interval would have to contain `T::max_value()`.
+    pub fn checked_with_width(start: T, width: T) -> Result<Self, MaxValueError> {
+        let end = start.checked_add(&width).ok_or(MaxValueError)?;
+        Ok(Self { start, end })
+    }
+
+    /// The interval covering every value of `T` except `T::max_value()`.
+    pub fn full() -> Self { Self::new(T::min_value(), T::max_value()) }
+
+    pub fn is_empty(&self) -> bool { self.start >= self.end }
+
+    pub fn width(&self) -> T {
+        if self.is_empty() {
+            T::zero()
+        } else {
+            self.end - self.start
+        }
+    }
+
+    pub fn contains(&self, value: T) -> bool { self.start <= value && value < self.end }
+
+    /// Returns the common part of both intervals, which may be empty.
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self::new(self.start.max(other.start), self.end.min(other.end))
+    }
+}
+
+/// An interval or piece would have to contain `T::max_value()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxValueError;
+
+impl fmt::Display for MaxValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interval would contain the maximum value of its type")
+    }
+}
+
+/// A set of values represented as sorted, disjoint and non-adjacent intervals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self { Self { intervals: Vec::new() } }
+}
+
+impl<T: PrimInt> IntervalSet<T> {
+    pub fn is_empty(&self) -> bool { self.intervals.is_empty() }
+
+    /// Iterates over the disjoint intervals in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Interval<T>> + '_ { self.intervals.iter().copied() }
+
+    pub fn min(&self) -> Option<T> { self.intervals.first().map(|interval| interval.start) }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self.intervals.partition_point(|interval| interval.end <= value);
+        self.intervals.get(index).is_some_and(|interval| interval.contains(value))
+    }
+
+    pub fn union(&self, other: &Self) -> Self { self.iter().chain(other.iter()).collect() }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (mut left, mut right) = (self.intervals.iter().peekable(), other.intervals.iter());
+        let mut intervals = Vec::new();
+
+        let mut right_next = right.next();
+        while let (Some(&a), Some(b)) = (left.peek(), right_next) {
+            let common = a.intersect(b);
+            if !common.is_empty() {
+                intervals.push(common);
+            }
+            if a.end < b.end {
+                left.next();
+            } else {
+                right_next = right.next();
+            }
+        }
+
+        Self { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let mut others = other.intervals.as_slice();
+
+        for &interval in &self.intervals {
+            others = &others[others.partition_point(|b| b.end <= interval.start)..];
+
+            let mut cursor = interval.start;
+            for b in others.iter().take_while(|b| b.start < interval.end) {
+                if cursor < b.start {
+                    intervals.push(Interval::new(cursor, b.start));
+                }
+                cursor = cursor.max(b.end);
+            }
+            if cursor < interval.end {
+                intervals.push(Interval::new(cursor, interval.end));
+            }
+        }
+
+        Self { intervals }
+    }
+}
+
+impl<T: PrimInt> From<Interval<T>> for IntervalSet<T> {
+    fn from(interval: Interval<T>) -> Self { [interval].into_iter().collect() }
+}
+
+impl<T: PrimInt> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut sorted: Vec<_> = iter.into_iter().filter(|interval| !interval.is_empty()).collect();
+        sorted.sort_unstable_by_key(|interval| interval.start);
+
+        let mut intervals: Vec<Interval<T>> = Vec::with_capacity(sorted.len());
+        for interval in sorted {
+            match intervals.last_mut() {
+                Some(last) if interval.start <= last.end => last.end = last.end.max(interval.end),
+                _ => intervals.push(interval),
+            }
+        }
+
+        Self { intervals }
+    }
+}
+
+/// Translates the values in `src` such that `src.start` maps to `dest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece<T> {
+    pub src:  Interval<T>,
+    pub dest: T,
+}
+
+impl<T: PrimInt> Piece<T> {
+    /// Creates a piece, failing if its image would have to contain `T::max_value()`.
+    pub fn new(src: Interval<T>, dest: T) -> Result<Self, MaxValueError> {
+        Interval::checked_with_width(dest, src.width())?;
+        Ok(Self { src, dest })
+    }
+
+    /// Maps a value in `self.src`.
+    pub fn apply(&self, value: T) -> T { self.dest + (value - self.src.start) }
+
+    /// Maps an interval contained in `self.src`.
+    pub fn apply_interval(&self, interval: Interval<T>) -> Interval<T> {
+        Interval::with_width(self.apply(interval.start), interval.width())
+    }
+
+    /// The interval that `self.src` maps to.
+    pub fn image(&self) -> Interval<T> { Interval::with_width(self.dest, self.src.width()) }
+
+    /// Returns the piece that maps `self.image()` back to `self.src`.
+    pub fn invert(&self) -> Self { Self { src: self.image(), dest: self.src.start } }
+}
+
+/// A piecewise offset map.
+///
+/// Values covered by a piece are translated by that piece;
+/// values not covered by any piece map to themselves.
+/// In particular, `T::max_value()` is never covered and always maps to itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalMap<T> {
+    /// sorted by `src.start`, with non-overlapping `src`
+    pieces: Vec<Piece<T>>,
+}
+
+impl<T> Default for IntervalMap<T> {
+    fn default() -> Self { Self { pieces: Vec::new() } }
+}
+
+impl<T: PrimInt> IntervalMap<T> {
+    pub fn pieces(&self) -> &[Piece<T>] { &self.pieces }
+
+    pub fn get(&self, value: T) -> T {
+        let index = self.pieces.partition_point(|piece| piece.src.end <= value);
+        match self.pieces.get(index) {
+            Some(piece) if piece.src.contains(value) => piece.apply(value),
+            _ => value,
+        }
+    }
+
+    /// Splits `within` into consecutive parts that are either covered by a single piece
+    /// or not covered by any piece.
+    fn segments(
+        &self,
+        within: Interval<T>,
+    ) -> impl Iterator<Item = (Interval<T>, Option<&Piece<T>>)> + '_ {
+        let first = self.pieces.partition_point(|piece| piece.src.end <= within.start);
+        let mut pieces = self.pieces[first..]
+            .iter()
+            .take_while(move |piece| piece.src.start < within.end)
+            .peekable();
+        let mut cursor = within.start;
+
+        iter::from_fn(move || {
+            if cursor >= within.end {
+                return None;
+            }
+
+            let (end, piece) = match pieces.peek() {
+                Some(piece) if piece.src.start <= cursor => {
+                    (piece.src.end.min(within.end), pieces.next())
+                }
+                Some(piece) => (piece.src.start, None),
+                None => (within.end, None),
+            };
+            let segment = Interval::new(cursor, end);
+            cursor = end;
+            Some((segment, piece))
+        })
+    }
+
+    /// Maps every value in `interval`, yielding the images in the order of their sources.
+    pub fn map_interval(&self, interval: Interval<T>) -> impl Iterator<Item = Interval<T>> + '_ {
+        self.segments(interval).map(|(segment, piece)| match piece {
+            Some(piece) => piece.apply_interval(segment),
+            None => segment,
+        })
+    }
+
+    pub fn map_set(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        set.iter().flat_map(|interval| self.map_interval(interval)).collect()
+    }
+
+    /// Returns every value that maps into `target`.
+    pub fn preimage(&self, target: Interval<T>) -> IntervalSet<T> {
+        let mapped = self.pieces.iter().filter_map(move |piece| {
+            let hit = piece.image().intersect(&target);
+            (!hit.is_empty()).then(|| piece.invert().apply_interval(hit))
+        });
+        let unmapped =
+            self.segments(target).filter_map(|(segment, piece)| piece.is_none().then_some(segment));
+        mapped.chain(unmapped).collect()
+    }
+
+    pub fn preimage_set(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        set.iter().flat_map(|interval| self.preimage(interval).intervals).collect()
+    }
+
+    /// Returns pieces mapping every image back to its source, sorted by `src.start`.
+    ///
+    /// Unmapped regions are included as identity pieces.
+    /// Unlike the pieces of an `IntervalMap`, the sources of the returned pieces may overlap,
+    /// since a value may have multiple preimages.
+    pub fn inverse_pieces(&self) -> Vec<Piece<T>> {
+        let mut pieces: Vec<_> = self
+            .segments(Interval::full())
+            .map(|(segment, piece)| match piece {
+                Some(piece) => Piece { src: piece.apply_interval(segment), dest: segment.start },
+                None => Piece { src: segment, dest: segment.start },
+            })
+            .collect();
+        pieces.sort_unstable_by_key(|piece| piece.src.start);
+        pieces
+    }
+
+    /// Returns the map equivalent to applying `self` and then `next`.
+    pub fn then(&self, next: &Self) -> Self {
+        let mut pieces: Vec<Piece<T>> = Vec::new();
+
+        for (segment, piece) in self.segments(Interval::full()) {
+            let image_start = piece.map_or(segment.start, |piece| piece.apply(segment.start));
+
+            for (sub, next_piece) in
+                next.segments(Interval::with_width(image_start, segment.width()))
+            {
+                let src =
+                    Interval::with_width(segment.start + (sub.start - image_start), sub.width());
+                let dest = next_piece.map_or(sub.start, |piece| piece.apply(sub.start));
+                if src.start == dest {
+                    continue; // identity is implicit
+                }
+
+                match pieces.last_mut() {
+                    Some(last) if last.src.end == src.start && last.apply(src.start) == dest => {
+                        last.src.end = src.end;
+                    }
+                    _ => pieces.push(Piece { src, dest }),
+                }
+            }
+        }
+
+        Self { pieces }
+    }
+}
+
+impl<T: PrimInt> FromIterator<Piece<T>> for IntervalMap<T> {
+    /// Panics if the sources of two pieces overlap.
+    fn from_iter<I: IntoIterator<Item = Piece<T>>>(iter: I) -> Self {
+        let mut pieces: Vec<_> = iter.into_iter().filter(|piece| !piece.src.is_empty()).collect();
+        pieces.sort_unstable_by_key(|piece| piece.src.start);
+        for pair in pieces.windows(2) {
+            assert!(pair[0].src.end <= pair[1].src.start, "pieces must not overlap");
+        }
+        Self { pieces }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Interval, IntervalMap, IntervalSet, Piece};
+    use crate::test_util::Xorshift;
+
+    fn set(intervals: &[(u32, u32)]) -> IntervalSet<u32> {
+        intervals.iter().map(|&(start, end)| Interval::new(start, end)).collect()
+    }
+
+    fn map(pieces: &[(u32, u32, u32)]) -> IntervalMap<u32> {
+        pieces
+            .iter()
+            .map(|&(dest, src, width)| Piece { src: Interval::with_width(src, width), dest })
+            .collect()
+    }
+
+    #[test]
+    fn test_set_merge() {
+        assert_eq!(set(&[(5, 8), (0, 2), (2, 3), (7, 10), (4, 4)]), set(&[(0, 3), (5, 10)]));
+    }
+
+    #[test]
+    fn test_set_ops() {
+        let a = set(&[(0, 5), (10, 15)]);
+        let b = set(&[(3, 12), (14, 20)]);
+        assert_eq!(a.union(&b), set(&[(0, 20)]));
+        assert_eq!(a.intersection(&b), set(&[(3, 5), (10, 12), (14, 15)]));
+        assert_eq!(a.difference(&b), set(&[(0, 3), (12, 14)]));
+        assert_eq!(b.difference(&a), set(&[(5, 10), (15, 20)]));
+        assert!(a.contains(4) && !a.contains(5) && a.contains(10));
+    }
+
+    #[test]
+    fn test_map_interval() {
+        let map = map(&[(50, 98, 2), (52, 50, 48)]);
+        assert_eq!(map.get(79), 81);
+        assert_eq!(map.get(99), 51);
+        assert_eq!(map.get(100), 100);
+        assert_eq!(
+            map.map_interval(Interval::new(40, 110)).collect::<Vec<_>>(),
+            [
+                Interval::new(40, 50),
+                Interval::new(52, 100),
+                Interval::new(50, 52),
+                Interval::new(100, 110)
+            ],
+        );
+    }
+
+    #[test]
+    fn test_map_then() {
+        let first = map(&[(50, 98, 2), (52, 50, 48)]);
+        let second = map(&[(0, 15, 37), (37, 52, 2), (39, 0, 15)]);
+        let composed = first.then(&second);
+        for value in 0..120 {
+            assert_eq!(composed.get(value), second.get(first.get(value)), "value {value}");
+        }
+    }
+
+    #[test]
+    fn test_max_value() {
+        use super::MaxValueError;
+
+        assert_eq!(
+            Interval::checked_with_width(u32::MAX - 3, 3),
+            Ok(Interval::new(u32::MAX - 3, u32::MAX))
+        );
+        assert_eq!(Interval::checked_with_width(u32::MAX - 3, 4), Err(MaxValueError));
+        assert_eq!(Interval::checked_with_width(u32::MAX, 1), Err(MaxValueError));
+
+        let src = Interval::with_width(0, 10);
+        assert!(Piece::new(src, u32::MAX - 10).is_ok());
+        assert_eq!(Piece::new(src, u32::MAX - 9), Err(MaxValueError));
+
+        // pieces reaching up to the maximum never cover it
+        let top = map(&[(0, u32::MAX - 10, 10)]);
+        let shift = map(&[(u32::MAX - 10, 0, 10)]);
+        assert_eq!(top.get(u32::MAX - 1), 9);
+        assert_eq!(top.get(u32::MAX), u32::MAX);
+        let composed = top.then(&shift);
+        for value in [0, 9, u32::MAX - 10, u32::MAX - 1, u32::MAX] {
+            assert_eq!(composed.get(value), shift.get(top.get(value)), "value {value}");
+        }
+    }
+
+    #[test]
+    fn test_preimage() {
+        let mut rng = Xorshift::new(29);
+        for _ in 0..100 {
+            let map: IntervalMap<u32> = (0..rng.below(5))
+                .map(|i| Piece {
+                    src:  Interval::with_width(i as u32 * 20 + rng.below(10) as u32, 10),
+                    dest: rng.below(100) as u32,
+                })
+                .collect();
+            let target = Interval::with_width(rng.below(100) as u32, rng.below(20) as u32);
+
+            let preimage = map.preimage(target);
+            for value in 0..150 {
+                assert_eq!(preimage.contains(value), target.contains(map.get(value)), "{map:?}");
+            }
+
+            for inverse in map.inverse_pieces() {
+                for location in inverse.src.start..inverse.src.end.min(200) {
+                    assert_eq!(map.get(inverse.apply(location)), location, "{map:?}");
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 #![feature(iter_array_chunks)]
 #![feature(impl_trait_in_assoc_type)]
+#![feature(portable_simd)]
 
+pub mod aho_corasick;
 pub mod day1;
 pub mod day10;
 pub mod day11;
@@ -17,6 +19,10 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
+pub mod interval;
+
+#[cfg(test)]
+mod test_util;
 
 aoc_runner_derive::aoc_lib! {
     year = 2023
@@ -0,0 +1,18 @@
+//! Helpers shared by the unit tests.
+
+/// A xorshift generator for reproducible pseudo-random test inputs.
+pub struct Xorshift(u64);
+
+impl Xorshift {
+    pub fn new(seed: u64) -> Self { Self(seed | 1) }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a value in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 { self.next_u64() % bound }
+}